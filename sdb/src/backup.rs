@@ -0,0 +1,117 @@
+use crate::compact::fresh;
+use crate::stats::roots;
+use crate::tx::{MutTxnEnv, Tx};
+use crate::{Change, Error};
+use sanakirja::{Commit, Env};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// the file being written by backup_to , renamed to path once complete
+fn partial(path: &Path) -> PathBuf {
+  let mut name = path.file_name().unwrap_or_default().to_os_string();
+  name.push(".partial");
+  path.with_file_name(name)
+}
+
+impl Tx {
+  // copy every declared tree (DbPages , indexes , ttl , log) of a read snapshot into a new file ,
+  // writers are not blocked . Returns the number of entries and the ids of the trees in the file
+  // that nothing declares : their types are unknown , so they are not copied
+  pub fn backup_to<P: AsRef<Path>>(&self, path: P) -> Result<(u64, Vec<usize>), Error> {
+    let path = path.as_ref();
+    fresh(path)?;
+    let tmp = partial(path);
+    // left by a failed backup
+    let _ = fs::remove_file(&tmp);
+    let written = self.write_backup(&tmp).and_then(|r| {
+      fs::rename(&tmp, path)?;
+      Ok(r)
+    });
+    if written.is_err() {
+      let _ = fs::remove_file(&tmp);
+    }
+    written
+  }
+
+  fn write_backup(&self, path: &Path) -> Result<(u64, Vec<usize>), Error> {
+    let len = fs::metadata(&self.file)?.len();
+    let src = Env::txn_begin(&self.env)?;
+    let env = Env::new(path, len, 1)?;
+    let mut dst: MutTxnEnv = Env::mut_txn_begin(&env)?;
+    let trees = self.tree_list();
+    let mut n = 0;
+    for (id, ops) in &trees {
+      n += (ops.copy)(&src, &mut dst, *id)?;
+    }
+    dst.commit()?;
+    let skipped = roots(&src)
+      .into_iter()
+      .map(|(id, _)| id)
+      .filter(|id| !trees.iter().any(|(declared, _)| declared == id))
+      .collect();
+    Ok((n, skipped))
+  }
+
+  // replace every declared tree by the one of a backup_to file , in one write tx . The log of
+  // this Tx is kept so that seq only grows : the restore is logged and sent to the watchers as a
+  // delete of every entry of the DbPages then a put of every restored one
+  pub fn restore_from<P: AsRef<Path>>(&self, path: P) -> Result<u64, Error> {
+    let path = path.as_ref();
+    // Env::new would create it
+    let len = fs::metadata(path)?.len();
+    let env = Env::new(path, len, 1)?;
+    let src = Env::txn_begin(&env)?;
+    let w = self.w()?;
+    let dst = unsafe { &mut *(w.ptr() as *mut MutTxnEnv) };
+    let restored = (|| -> Result<u64, Error> {
+      // the last commit , the write tx is the only writer
+      let old = Env::txn_begin(&self.env)?;
+      let on = self.feed.on();
+      let mut puts = vec![];
+      let mut n = 0;
+      for (id, ops) in self.tree_list() {
+        if Some(id) == self.feed.log() {
+          continue;
+        }
+        if on && self.recorded(id, &ops) {
+          for (key, v) in (ops.entries)(&old, id)? {
+            self.feed.push(Change {
+              id,
+              key,
+              old: Some(v),
+              new: None,
+            });
+          }
+          // the puts of a DbPage before the ones of its ttl tree , see Tx::apply_change
+          for (key, v) in (ops.entries)(&src, id)? {
+            puts.push((
+              !ops.page,
+              Change {
+                id,
+                key,
+                old: None,
+                new: Some(v),
+              },
+            ));
+          }
+        }
+        n += (ops.copy)(&src, dst, id)?;
+      }
+      puts.sort_by_key(|(ttl, _)| *ttl);
+      for (_, change) in puts {
+        self.feed.push(change);
+      }
+      Ok(n)
+    })();
+    match restored {
+      Ok(n) => {
+        w.commit()?;
+        Ok(n)
+      }
+      Err(err) => {
+        w.abort();
+        Err(err)
+      }
+    }
+  }
+}
@@ -0,0 +1,193 @@
+use crate::free::free_list;
+use crate::tx::{Tx, TxnEnv};
+use crate::walk::PAGE_SIZE;
+use crate::{Error, Verify};
+use sanakirja::btree::{self, BTreePage};
+use sanakirja::{Env, RootDb, UnsizedStorable};
+use std::any::type_name;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+// result of Tx::check
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+  pub trees: usize,
+  pub entries: u64,
+  pub pages: u64,
+  pub problems: Vec<Problem>,
+}
+
+impl Report {
+  pub fn ok(&self) -> bool {
+    self.problems.is_empty()
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+  // entry n of the tree is smaller than the previous one
+  Order {
+    id: usize,
+    entry: u64,
+  },
+  // entry n of the tree fails Verify for the type ty
+  Corruption {
+    id: usize,
+    entry: u64,
+    ty: &'static str,
+  },
+  // a page is referenced by two trees , or twice by one
+  SharedPage {
+    offset: u64,
+    ids: (usize, usize),
+  },
+  // a page of the tree is also in the free list , the next write tx may overwrite it
+  FreePage {
+    id: usize,
+    offset: u64,
+  },
+  // a page beyond the end of the file , the root or a child ; the entries of the tree are not
+  // checked
+  OutOfFile {
+    id: usize,
+    offset: u64,
+  },
+  // a page of the free list of sanakirja beyond the end of the file ; no page is checked
+  // against the free list
+  FreeList {
+    offset: u64,
+  },
+  // the tree could not be read
+  Unreadable {
+    id: usize,
+    error: String,
+  },
+}
+
+// iterate the entries of the tree , registered in TreeOps
+pub(crate) fn check<K, V, P>(txn: &TxnEnv, id: usize, problems: &mut Vec<Problem>) -> u64
+where
+  K: UnsizedStorable + Verify + ?Sized,
+  V: UnsizedStorable + Verify + ?Sized,
+  P: BTreePage<K, V>,
+{
+  let db = match txn.root_db::<K, V, P>(id) {
+    Some(db) => db,
+    None => return 0,
+  };
+  let mut n = 0;
+  let unreadable = |e: sanakirja::Error| Problem::Unreadable {
+    id,
+    error: e.to_string(),
+  };
+  let iter = match btree::iter(txn, &db, None) {
+    Ok(iter) => iter,
+    Err(e) => {
+      problems.push(unreadable(e));
+      return 0;
+    }
+  };
+  let mut prev: Option<(&K, &V)> = None;
+  for entry in iter {
+    let (k, v) = match entry {
+      Ok(kv) => kv,
+      Err(e) => {
+        problems.push(unreadable(e));
+        break;
+      }
+    };
+    let bad = if !k.verify() {
+      Some(type_name::<K>())
+    } else if !v.verify() {
+      Some(type_name::<V>())
+    } else {
+      None
+    };
+    if let Some(ty) = bad {
+      // no compare on garbage , the next entry is compared to the last good one
+      problems.push(Problem::Corruption { id, entry: n, ty });
+    } else {
+      if let Some((pk, pv)) = prev {
+        let order = pk.compare(txn, k).then_with(|| pv.compare(txn, v));
+        if order == Ordering::Greater {
+          problems.push(Problem::Order { id, entry: n });
+        }
+      }
+      prev = Some((k, v));
+    }
+    n += 1;
+  }
+  n
+}
+
+impl Tx {
+  // check every declared tree in one read snapshot : entries in order and Verify , pages inside
+  // the file , owned by a single tree and not in the free list
+  pub fn check(&self) -> Result<Report, Error> {
+    let txn = Env::txn_begin(&self.env)?;
+    let file = fs::metadata(&self.file)?.len();
+    let free_list = free_list(&txn, self.max_tx, file)?;
+    let mut report = Report::default();
+    if let Some(offset) = free_list.outside {
+      report.problems.push(Problem::FreeList { offset });
+    }
+    let free: HashSet<u64> = free_list.pages.into_iter().collect();
+    let mut owner: HashMap<u64, usize> = HashMap::new();
+    for (id, ops) in self.tree_list() {
+      report.trees += 1;
+      // loading a root beyond the end of the map would crash
+      if let Some(root) = txn.root(id) {
+        let offset = root & !(PAGE_SIZE - 1);
+        if offset + PAGE_SIZE > file {
+          report.problems.push(Problem::OutOfFile { id, offset });
+          continue;
+        }
+      }
+      let problems = &mut report.problems;
+      let mut pages = 0;
+      let mut out_of_file = false;
+      let walked = (ops.pages)(&txn, id, &mut |page| {
+        pages += 1;
+        if let Some(other) = owner.insert(page.offset, id) {
+          problems.push(Problem::SharedPage {
+            offset: page.offset,
+            ids: (other, id),
+          });
+          // already walked , and may be a cycle
+          return false;
+        }
+        if free.contains(&page.offset) {
+          problems.push(Problem::FreePage {
+            id,
+            offset: page.offset,
+          });
+        }
+        let mut descend = true;
+        for child in &page.children {
+          let offset = child & !(PAGE_SIZE - 1);
+          if offset + PAGE_SIZE > file {
+            problems.push(Problem::OutOfFile { id, offset });
+            out_of_file = true;
+            descend = false;
+          }
+        }
+        descend
+      });
+      report.pages += pages;
+      if let Err(e) = walked {
+        report.problems.push(Problem::Unreadable {
+          id,
+          error: e.to_string(),
+        });
+        continue;
+      }
+      // the iteration of the entries would load the page beyond the end of the map
+      if out_of_file {
+        continue;
+      }
+      report.entries += (ops.check)(&txn, id, &mut report.problems);
+    }
+    Ok(report)
+  }
+}
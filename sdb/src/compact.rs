@@ -0,0 +1,97 @@
+use crate::tx::{MutTxnEnv, Tx};
+use crate::walk::PAGE_SIZE;
+use crate::Error;
+use sanakirja::{Commit, Env};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// file usage , see Tx::space
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Space {
+  pub file: u64,
+  // pages of the declared trees and the root pages
+  pub live: u64,
+  // free pages , plus the pages of trees no DbPage declares ; what compact_into would reclaim
+  // once they are declared
+  pub free: u64,
+}
+
+pub(crate) fn fresh(path: &Path) -> Result<(), Error> {
+  if path.exists() {
+    return Err(
+      io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} exists", path.display()),
+      )
+      .into(),
+    );
+  }
+  Ok(())
+}
+
+impl Tx {
+  pub fn space(&self) -> Result<Space, Error> {
+    let txn = Env::txn_begin(&self.env)?;
+    let mut pages = self.max_tx as u64;
+    for (id, ops) in self.tree_list() {
+      (ops.pages)(&txn, id, &mut |_| {
+        pages += 1;
+        true
+      })?;
+    }
+    let file = fs::metadata(&self.file)?.len();
+    let live = pages * PAGE_SIZE;
+    Ok(Space {
+      file,
+      live,
+      free: file.saturating_sub(live),
+    })
+  }
+
+  // rewrite every declared tree densely into new_path , then rename it over the file of the Tx ;
+  // the new file is opened before the rename , so the Tx always has a file , and is left as it was
+  // on error . Error::UnknownTree if a tree of the file is not declared : its types are unknown ,
+  // so it could not be copied . Returns the reclaimed bytes
+  pub fn compact_into<P: AsRef<Path>>(&mut self, new_path: P) -> Result<u64, Error> {
+    if self.read_only {
+      return Err(Error::ReadOnly);
+    }
+    let new_path = new_path.as_ref();
+    fresh(new_path)?;
+    let trees = self.tree_list();
+    if let Some((id, _)) = self
+      .roots()?
+      .into_iter()
+      .find(|(id, _)| !trees.iter().any(|(declared, _)| declared == id))
+    {
+      return Err(Error::UnknownTree(id));
+    }
+    let before = fs::metadata(&self.file)?.len();
+    let compacted = (|| -> Result<(Env, u64), Error> {
+      let env = Env::new(new_path, self.init_size, self.max_tx)?;
+      {
+        let src = Env::txn_begin(&self.env)?;
+        let mut dst: MutTxnEnv = Env::mut_txn_begin(&env)?;
+        for (id, ops) in &trees {
+          (ops.copy)(&src, &mut dst, *id)?;
+        }
+        dst.commit()?;
+      }
+      let after = fs::metadata(new_path)?.len();
+      fs::rename(new_path, &self.file)?;
+      Ok((env, after))
+    })();
+    match compacted {
+      Ok((env, after)) => {
+        // the old env maps the replaced file until it is dropped here
+        self.env = env;
+        Ok(before.saturating_sub(after))
+      }
+      Err(err) => {
+        let _ = fs::remove_file(new_path);
+        Err(err)
+      }
+    }
+  }
+}
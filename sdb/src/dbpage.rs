@@ -1,8 +1,17 @@
-use crate::tx::{Tx, TxnEnv};
+use crate::tx::{MutTxnEnv, Tx, TxnEnv};
+use crate::{
+  ttl_clear, ttl_set, Change, Error, Index, IndexKey, Owned, Ttl, Verified, Verify, View, WriteTx,
+  UP,
+};
 pub use sanakirja::btree::page::Page;
 use sanakirja::btree::{BTreeMutPage, BTreePage, Iter, RevIter};
-use sanakirja::{Error, LoadPage, Storable};
+use sanakirja::{Storable, UnsizedStorable};
+use std::borrow::Cow;
 use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::Duration;
 
 macro_rules! db_page_r {
   ($self:ident, $db:ident, $fn:expr) => {{
@@ -16,19 +25,202 @@ macro_rules! db_page_w {
   ($self:ident, $db:ident, $fn:expr) => {{
     let tx = $self.tx.w()?;
     let mut $db = tx.db($self);
-    $fn
+    // an explicit commit , so that Error::Full is returned rather than kept for
+    // Tx::take_drop_error ; a failed write leaves nothing half done
+    match $fn {
+      Ok(r) => {
+        tx.commit()?;
+        Ok(r)
+      }
+      Err(err) => {
+        tx.abort();
+        Err(err)
+      }
+    }
   }};
 }
 
 impl<
     'a,
-    K: ?Sized + Storable + PartialEq,
-    V: ?Sized + Storable + PartialEq,
+    K: ?Sized + Storable + UnsizedStorable + PartialEq + Verify,
+    V: ?Sized + Storable + UnsizedStorable + PartialEq + Verify,
     P: BTreeMutPage<K, V> + BTreePage<K, V>,
     RK: ?Sized + Encode<K>,
     RV: ?Sized + Encode<V>,
   > DbPage<'a, K, V, P, RK, RV>
 {
+  // the changes of this tree whose key is in range , sent after each commit
+  pub fn watch<R: RangeBounds<K> + Send + Sync + 'static>(&self, range: R) -> Receiver<Change>
+  where
+    K: PartialOrd,
+  {
+    let id = self.id;
+    self
+      .tx
+      .feed
+      .subscribe(Some(Box::new(move |change: &Change| {
+        change.id == id
+          && Owned::<K>::from_bytes(&change.key).map_or(false, |k| range.contains(&*k))
+      })))
+  }
+
+  // index key -> primary key in the tree `id` , kept up to date by put / rm / upsert
+  pub fn with_index<IK: IndexKey, F: Fn(&V) -> IK + Send + Sync + 'static>(
+    self,
+    name: &str,
+    id: usize,
+    f: F,
+  ) -> Self
+  where
+    K: 'static,
+    V: 'static,
+    P: 'static,
+    RK: 'static,
+    RV: 'static,
+  {
+    self.add_index(name, id, false, f)
+  }
+
+  // same as with_index , put / upsert fail with Error::ConstraintViolation on a duplicate
+  pub fn with_unique<IK: IndexKey, F: Fn(&V) -> IK + Send + Sync + 'static>(
+    self,
+    name: &str,
+    id: usize,
+    f: F,
+  ) -> Self
+  where
+    K: 'static,
+    V: 'static,
+    P: 'static,
+    RK: 'static,
+    RV: 'static,
+  {
+    self.add_index(name, id, true, f)
+  }
+
+  fn add_index<IK: IndexKey, F: Fn(&V) -> IK + Send + Sync + 'static>(
+    mut self,
+    name: &str,
+    id: usize,
+    unique: bool,
+    f: F,
+  ) -> Self
+  where
+    K: 'static,
+    V: 'static,
+    P: 'static,
+    RK: 'static,
+    RV: 'static,
+  {
+    // the expiry registered by with_ttl keeps the indexes declared before it
+    assert!(self.ttl.is_none(), "declare indexes before with_ttl");
+    self.tx.register::<[u8], K, UP<[u8], K>>(id, false);
+    self.index.push(Index {
+      name: name.into(),
+      id,
+      unique,
+      key: Arc::new(move |v| f(v).index_key()),
+    });
+    self.set_writer();
+    self
+  }
+
+  // entries of put_with_ttl are skipped by reads once expired , and deleted by Tx::expire_now .
+  // put_with_ttl sets the deadline of k , put and upsert keep it , rm of the last value clears it
+  pub fn with_ttl(mut self, id: usize, deadline_id: usize) -> Self
+  where
+    K: 'static,
+    V: 'static,
+    P: 'static,
+    RK: 'static,
+    RV: 'static,
+  {
+    let ttl = Ttl { id, deadline_id };
+    self.tx.register::<K, u64, UP<K, u64>>(id, false);
+    self.tx.register::<u64, K, UP<u64, K>>(deadline_id, false);
+    self.ttl = Some(ttl);
+    self.set_writer();
+    // the deadline tree follows the changes of the tree id
+    self.tx.writers.lock().unwrap().insert(
+      id,
+      Arc::new(move |w: &WriteTx, change: &Change| {
+        let txn = unsafe { &mut *(w.ptr() as *mut MutTxnEnv) };
+        let k = Owned::<K>::from_bytes(&change.key)?;
+        match &change.new {
+          Some(deadline) => ttl_set(txn, ttl, &*k, *Owned::<u64>::from_bytes(deadline)?)?,
+          None => ttl_clear(txn, ttl, &*k)?,
+        };
+        if w.tx.feed.on() {
+          w.tx.feed.push(change.clone());
+        }
+        Ok(())
+      }),
+    );
+    let id = self.id;
+    let index = self.index.clone();
+    self.tx.expire.lock().unwrap().insert(
+      id,
+      Arc::new(move |tx: &Tx, now: u64, limit: usize| {
+        DbPage::<K, V, P, RK, RV> {
+          tx,
+          id,
+          index: index.clone(),
+          ttl: Some(ttl),
+          _kvp: PhantomData,
+        }
+        .expire(now, limit)
+      }),
+    );
+    self
+  }
+
+  // Changes of this tree applied by Tx::apply_change go through put / rm , with the indexes
+  // and ttl declared so far
+  fn set_writer(&self)
+  where
+    K: 'static,
+    V: 'static,
+    P: 'static,
+    RK: 'static,
+    RV: 'static,
+  {
+    let (id, index, ttl) = (self.id, self.index.clone(), self.ttl);
+    self.tx.writers.lock().unwrap().insert(
+      id,
+      Arc::new(move |w: &WriteTx, change: &Change| {
+        let page = DbPage::<K, V, P, RK, RV> {
+          tx: w.tx,
+          id,
+          index: index.clone(),
+          ttl,
+          _kvp: PhantomData,
+        };
+        w.db(&page).apply(change)
+      }),
+    );
+  }
+
+  // delete the keys whose deadline <= now (ms since the unix epoch) , at most limit
+  pub fn expire(&self, now: u64, limit: usize) -> Result<usize, Error> {
+    db_page_w!(self, db, db.expire(now, limit))
+  }
+
+  pub fn put_with_ttl(&self, k: &RK, v: &RV, ttl: Duration) -> Result<bool, Error> {
+    db_page_w!(self, db, db.put_with_ttl(k, v, ttl))
+  }
+
+  pub fn by_index<IK: IndexKey + ?Sized>(
+    &self,
+    name: &str,
+    ik: &IK,
+  ) -> Result<Vec<(&'a K, &'a V)>, Error> {
+    db_page_r!(self, db, db.by_index(name, ik))
+  }
+
+  pub fn rebuild_index(&self, name: &str) -> Result<usize, Error> {
+    db_page_w!(self, db, db.rebuild_index(name))
+  }
+
   pub fn upsert(&self, k: &RK, v: &RV) -> std::result::Result<bool, Error> {
     db_page_w!(self, db, db.upsert(k, v))
   }
@@ -41,11 +233,25 @@ impl<
     db_page_w!(self, db, db.rm(k))
   }
 
-  pub fn one(&self, k: &'a RK) -> Result<Option<&'a V>, <TxnEnv as LoadPage>::Error> {
+  pub fn one(&self, k: &'a RK) -> Result<Option<&'a V>, Error> {
     db_page_r!(self, db, db.one(k))
   }
 
-  pub fn exist(&self, k: &RK, v: &RV) -> Result<bool, <TxnEnv as LoadPage>::Error> {
+  pub fn get(&self, k: &RK) -> Result<Option<RV>, Error>
+  where
+    RV: Sized + for<'x> From<&'x V>,
+  {
+    db_page_r!(self, db, db.get(k))
+  }
+
+  pub fn get_all(&self, k: &RK) -> Result<Vec<RV>, Error>
+  where
+    RV: Sized + for<'x> From<&'x V>,
+  {
+    db_page_r!(self, db, db.get_all(k))
+  }
+
+  pub fn exist(&self, k: &RK, v: &RV) -> Result<bool, Error> {
     db_page_r!(self, db, db.exist(k, v))
   }
 
@@ -56,10 +262,7 @@ impl<
   pub fn key_iter(
     &self,
     k: &'a RK,
-  ) -> Result<
-    Box<dyn Iterator<Item = Result<(&'a K, &'a V), <TxnEnv as LoadPage>::Error>> + 'a>,
-    <TxnEnv as LoadPage>::Error,
-  > {
+  ) -> Result<Box<dyn Iterator<Item = Result<(&'a K, &'a V), Error>> + 'a>, Error> {
     db_page_r!(self, db, db.key_iter(k))
   }
 
@@ -67,7 +270,7 @@ impl<
     &self,
     k: OptionK,
     v: OptionV,
-  ) -> Result<Iter<TxnEnv, K, V, P>, <TxnEnv as LoadPage>::Error> {
+  ) -> Result<Verified<'a, Iter<TxnEnv, K, V, P>, K>, Error> {
     db_page_r!(self, db, db.iter(k.into(), v.into()))
   }
 
@@ -75,11 +278,38 @@ impl<
     &self,
     k: OptionK,
     v: OptionV,
-  ) -> Result<RevIter<TxnEnv, K, V, P>, <TxnEnv as LoadPage>::Error> {
+  ) -> Result<Verified<'a, RevIter<TxnEnv, K, V, P>, K>, Error> {
     db_page_r!(self, db, db.riter(k.into(), v.into()))
   }
 }
 
+impl<
+    'a,
+    K: ?Sized + Storable + UnsizedStorable + PartialEq + Verify,
+    P: BTreeMutPage<K, [u8]> + BTreePage<K, [u8]>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<[u8]>,
+  > DbPage<'a, K, [u8], P, RK, RV>
+{
+  pub fn one_view<X: View>(&self, k: &RK) -> Result<Option<Cow<'a, X>>, Error> {
+    db_page_r!(self, db, db.one_view(k))
+  }
+
+  pub fn iter_view<X: View, OptionK: Into<Option<&'a RK>>>(
+    &self,
+    k: OptionK,
+  ) -> Result<impl Iterator<Item = Result<(&'a K, Cow<'a, X>), Error>>, Error> {
+    db_page_r!(self, db, db.iter_view(k))
+  }
+
+  pub fn riter_view<X: View, OptionK: Into<Option<&'a RK>>>(
+    &self,
+    k: OptionK,
+  ) -> Result<impl Iterator<Item = Result<(&'a K, Cow<'a, X>), Error>>, Error> {
+    db_page_r!(self, db, db.riter_view(k))
+  }
+}
+
 pub struct DbPage<
   'a,
   K: ?Sized + Storable + PartialEq,
@@ -90,6 +320,8 @@ pub struct DbPage<
 > {
   pub(crate) tx: &'a Tx,
   pub id: usize,
+  pub(crate) index: Vec<Index<V>>,
+  pub(crate) ttl: Option<Ttl>,
   pub(crate) _kvp: PhantomData<(&'a K, &'a V, &'a P, &'a RK, &'a RV)>,
 }
 
@@ -0,0 +1,49 @@
+use crate::walk::{walk, PAGE_SIZE};
+use crate::{Encode, Error, TxDb};
+use sanakirja::btree::{BTreeMutPage, BTreePage};
+use sanakirja::{LoadPage, RootDb, Storable, UnsizedStorable};
+use std::io::Write;
+
+impl<
+    'a,
+    K: Storable + UnsizedStorable + PartialEq + ?Sized,
+    V: Storable + UnsizedStorable + PartialEq + ?Sized,
+    T: LoadPage<Error = sanakirja::Error> + RootDb,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  > TxDb<'a, K, V, T, P, RK, RV>
+{
+  // the pages of the tree as a graphviz digraph : offset , entries and bytes of each page , an
+  // edge per child , e.g. sdb-cli <dir> dot <id> | dot -Tsvg > tree.svg
+  pub fn to_dot<W: Write>(&self, mut out: W) -> Result<(), Error> {
+    let tx = unsafe { &*self.tx };
+    writeln!(out, "digraph tree{} {{", self.id)?;
+    writeln!(out, "  node [shape=record];")?;
+    let mut io: std::io::Result<()> = Ok(());
+    let db = match self.root() {
+      Some(db) => db,
+      None => {
+        writeln!(out, "}}")?;
+        return Ok(());
+      }
+    };
+    walk(tx, &db, &mut |page| {
+      io = (|| {
+        writeln!(
+          out,
+          "  p{0} [label=\"{0} | {1} entries | {2} bytes\"];",
+          page.offset, page.entries, page.bytes
+        )?;
+        for child in &page.children {
+          writeln!(out, "  p{} -> p{};", page.offset, child & !(PAGE_SIZE - 1))?;
+        }
+        Ok(())
+      })();
+      io.is_ok()
+    })?;
+    io?;
+    writeln!(out, "}}")?;
+    Ok(())
+  }
+}
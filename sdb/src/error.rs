@@ -0,0 +1,74 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+  Db(sanakirja::Error),
+  Io(std::io::Error),
+  // a value read from disk is not a valid bit pattern of its type (e.g. unknown enum discriminant)
+  Corruption(&'static str),
+  // no index with this name was declared with DbPage::with_index
+  NoIndex(String),
+  // put / upsert would map a key of this unique index to a second primary key
+  ConstraintViolation(String),
+  // put_with_ttl on the DbPage with this id , declared without DbPage::with_ttl
+  NoTtl(usize),
+  // the Tx was opened without TxArgs::Log
+  NoLog,
+  // the changes before this seq were truncated from the log before a replica applied them
+  LogGap(u64),
+  // a Change of a tree that no DbPage of the Tx declares
+  UnknownTree(usize),
+  // DbPage::import , the line of the input that could not be parsed
+  Parse(u64, String),
+  // a write on a Tx opened with TxArgs::ReadOnly
+  ReadOnly,
+  // the file size when a commit was refused by TxArgs::MaxSize , see Tx::on_full
+  Full(u64),
+  // Intent::put / rm1 on a DbPage whose Tx is not a part of the Coordinator
+  NotAPart,
+  // the journaled commit with this seq is not applied to every part , see Coordinator::recover
+  Pending(u64),
+}
+
+impl From<sanakirja::Error> for Error {
+  fn from(err: sanakirja::Error) -> Self {
+    Error::Db(err)
+  }
+}
+
+impl From<std::io::Error> for Error {
+  fn from(err: std::io::Error) -> Self {
+    Error::Io(err)
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::Db(err) => err.fmt(f),
+      Error::Io(err) => err.fmt(f),
+      Error::Corruption(ty) => write!(f, "corrupted value of type {}", ty),
+      Error::NoIndex(name) => write!(f, "no index named {}", name),
+      Error::LogGap(seq) => write!(f, "change log truncated before seq {}", seq),
+      Error::UnknownTree(id) => write!(f, "no db declared with id {}", id),
+      Error::Parse(line, msg) => write!(f, "line {} : {}", line, msg),
+      Error::ReadOnly => write!(f, "the Tx is read only"),
+      Error::Full(len) => write!(f, "file size {} over TxArgs::MaxSize", len),
+      Error::NoLog => write!(f, "no change log , see TxArgs::Log"),
+      Error::NoTtl(id) => write!(f, "db {} has no ttl", id),
+      Error::ConstraintViolation(name) => write!(f, "duplicate key in unique index {}", name),
+      Error::NotAPart => write!(f, "the Tx is not a part of the Coordinator"),
+      Error::Pending(seq) => write!(f, "journaled commit {} is not applied , recover first", seq),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Db(err) => Some(err),
+      Error::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,319 @@
+use crate::{DbPage, Encode, Error, Owned, Verify};
+use sanakirja::btree::{BTreeMutPage, BTreePage};
+use sanakirja::{Storable, UnsizedStorable};
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+// puts per write transaction of DbPage::import
+const BATCH: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+  // one {"key":..,"value":..} object per line
+  Jsonl,
+  // a key,value header then one entry per line
+  Csv,
+}
+
+// text form of a key / value for DbPage::export / import ; serde types can implement it with
+// serde_json in the crate that declares them
+pub trait Codec: UnsizedStorable {
+  // written as a json number rather than a string
+  const NUMBER: bool = false;
+  fn encode(&self) -> String;
+  fn decode(s: &str) -> Result<Owned<Self>, String>;
+}
+
+macro_rules! codec_int {
+  ( $( $x:ty ),* ) => {
+    $(impl Codec for $x {
+      const NUMBER: bool = true;
+      fn encode(&self) -> String {
+        self.to_string()
+      }
+      fn decode(s: &str) -> Result<Owned<Self>, String> {
+        s.parse::<$x>().map(|n| Owned::new(&n)).map_err(|e| format!("{} : {}", s, e))
+      }
+    })*
+  };
+}
+
+codec_int!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize);
+
+// hex
+impl Codec for [u8] {
+  fn encode(&self) -> String {
+    let mut s = String::with_capacity(self.len() * 2);
+    for b in self {
+      write!(s, "{:02x}", b).unwrap();
+    }
+    s
+  }
+
+  fn decode(s: &str) -> Result<Owned<Self>, String> {
+    if s.len() % 2 != 0 || !s.is_ascii() {
+      return Err(format!("{} : not hex", s));
+    }
+    let li = (0..s.len())
+      .step_by(2)
+      .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+      .collect::<Result<Vec<u8>, _>>()
+      .map_err(|e| format!("{} : {}", s, e))?;
+    Ok(Owned::new(&li[..]))
+  }
+}
+
+impl Format {
+  fn line<K: Codec + ?Sized, V: Codec + ?Sized>(self, out: &mut String, k: &K, v: &V) {
+    match self {
+      Format::Jsonl => {
+        out.push_str("{\"key\":");
+        json(out, &k.encode(), K::NUMBER);
+        out.push_str(",\"value\":");
+        json(out, &v.encode(), V::NUMBER);
+        out.push_str("}\n");
+      }
+      Format::Csv => {
+        csv(out, &k.encode());
+        out.push(',');
+        csv(out, &v.encode());
+        out.push('\n');
+      }
+    }
+  }
+
+  fn parse<K: Codec + ?Sized, V: Codec + ?Sized>(
+    self,
+    line: &str,
+  ) -> Result<(Owned<K>, Owned<V>), String> {
+    let (k, v) = match self {
+      Format::Jsonl => {
+        let li = json_fields(line)?;
+        let field = |name: &str| {
+          li.iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+            .ok_or_else(|| format!("no field {}", name))
+        };
+        (field("key")?.to_string(), field("value")?.to_string())
+      }
+      Format::Csv => {
+        let mut li = csv_fields(line)?;
+        if li.len() != 2 {
+          return Err(format!("{} fields , expected key,value", li.len()));
+        }
+        let v = li.pop().unwrap();
+        (li.pop().unwrap(), v)
+      }
+    };
+    Ok((K::decode(&k)?, V::decode(&v)?))
+  }
+}
+
+fn json(out: &mut String, s: &str, number: bool) {
+  if number {
+    out.push_str(s);
+    return;
+  }
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+}
+
+// the fields of a flat json object , strings unescaped and other values as written
+fn json_fields(line: &str) -> Result<Vec<(String, String)>, String> {
+  let mut it = line.trim().chars().peekable();
+  let mut li = vec![];
+  if it.next() != Some('{') {
+    return Err("expected {".into());
+  }
+  loop {
+    while it.peek().map_or(false, |c| c.is_whitespace() || *c == ',') {
+      it.next();
+    }
+    match it.next() {
+      Some('}') => return Ok(li),
+      Some('"') => {}
+      _ => return Err("expected a field name".into()),
+    }
+    let name = json_str(&mut it)?;
+    while it.peek().map_or(false, |c| c.is_whitespace()) {
+      it.next();
+    }
+    if it.next() != Some(':') {
+      return Err(format!("expected : after {}", name));
+    }
+    while it.peek().map_or(false, |c| c.is_whitespace()) {
+      it.next();
+    }
+    let val = if it.peek() == Some(&'"') {
+      it.next();
+      json_str(&mut it)?
+    } else {
+      let mut s = String::new();
+      while let Some(c) = it.peek() {
+        if *c == ',' || *c == '}' || c.is_whitespace() {
+          break;
+        }
+        s.push(*c);
+        it.next();
+      }
+      s
+    };
+    li.push((name, val));
+  }
+}
+
+// after the opening quote
+fn json_str(it: &mut impl Iterator<Item = char>) -> Result<String, String> {
+  let mut s = String::new();
+  loop {
+    match it.next() {
+      None => return Err("unterminated string".into()),
+      Some('"') => return Ok(s),
+      Some('\\') => match it.next() {
+        Some('n') => s.push('\n'),
+        Some('r') => s.push('\r'),
+        Some('t') => s.push('\t'),
+        Some('u') => {
+          let hex: String = it.by_ref().take(4).collect();
+          let c = u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("bad escape \\u{}", hex))?;
+          s.push(c);
+        }
+        Some(c) => s.push(c),
+        None => return Err("unterminated string".into()),
+      },
+      Some(c) => s.push(c),
+    }
+  }
+}
+
+fn csv(out: &mut String, s: &str) {
+  if s.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+    out.push('"');
+    out.push_str(&s.replace('"', "\"\""));
+    out.push('"');
+  } else {
+    out.push_str(s);
+  }
+}
+
+fn csv_fields(line: &str) -> Result<Vec<String>, String> {
+  let mut li = vec![];
+  let mut it = line.trim_end_matches(&['\r', '\n'][..]).chars().peekable();
+  loop {
+    let mut s = String::new();
+    if it.peek() == Some(&'"') {
+      it.next();
+      loop {
+        match it.next() {
+          None => return Err("unterminated quote".into()),
+          Some('"') if it.peek() == Some(&'"') => {
+            it.next();
+            s.push('"');
+          }
+          Some('"') => break,
+          Some(c) => s.push(c),
+        }
+      }
+    }
+    loop {
+      match it.next() {
+        None => {
+          li.push(s);
+          return Ok(li);
+        }
+        Some(',') => break,
+        Some(c) => s.push(c),
+      }
+    }
+    li.push(s);
+  }
+}
+
+impl<
+    'a,
+    K: ?Sized + Storable + UnsizedStorable + PartialEq + Verify + Codec,
+    V: ?Sized + Storable + UnsizedStorable + PartialEq + Verify + Codec,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  > DbPage<'a, K, V, P, RK, RV>
+{
+  // every entry of one read snapshot , expired keys skipped ; returns the number of entries
+  pub fn export<W: Write>(&self, mut out: W, format: Format) -> Result<u64, Error> {
+    let tx = self.tx.r()?;
+    let db = tx.db(self);
+    if format == Format::Csv {
+      out.write_all(b"key,value\n")?;
+    }
+    let mut n = 0;
+    let mut line = String::new();
+    for entry in db.iter(None, None)? {
+      let (k, v) = entry?;
+      line.clear();
+      format.line(&mut line, k, v);
+      out.write_all(line.as_bytes())?;
+      n += 1;
+    }
+    out.flush()?;
+    Ok(n)
+  }
+
+  // put every entry of an export , through indexes and the change feed , in write transactions
+  // of BATCH entries ; on error the batches before the failing line stay committed
+  pub fn import<R: BufRead>(&self, input: R, format: Format) -> Result<u64, Error> {
+    let mut lines = input.lines();
+    let mut no = 0;
+    let mut n = 0;
+    loop {
+      let w = self.tx.w()?;
+      let mut db = w.db(self);
+      let batch = (|| {
+        let mut batch = 0;
+        while batch < BATCH {
+          let line = match lines.next() {
+            None => break,
+            Some(line) => line?,
+          };
+          no += 1;
+          if line.trim().is_empty() || (no == 1 && format == Format::Csv && line == "key,value") {
+            continue;
+          }
+          let (k, v) = format
+            .parse::<K, V>(&line)
+            .map_err(|msg| Error::Parse(no, msg))?;
+          db.put_raw(&k, &v)?;
+          batch += 1;
+        }
+        Ok(batch)
+      })();
+      match batch {
+        Ok(batch) => {
+          w.commit()?;
+          n += batch as u64;
+          if batch < BATCH {
+            return Ok(n);
+          }
+        }
+        Err(err) => {
+          w.abort();
+          return Err(err);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,305 @@
+use crate::tx::{MutTxnEnv, Tx};
+use crate::{tree, Error, UP};
+use sanakirja::btree::{self, Db_};
+use sanakirja::{Env, LoadPage, RootDb, UnsizedStorable};
+use std::convert::TryFrom;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+// a primary tree entry changed by a committed write tx , values are the on-page bytes ,
+// decode them with Owned::from_bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+  // DbPage id , or the Ttl::id of its ttl : the value is then the u64 deadline
+  pub id: usize,
+  pub key: Vec<u8>,
+  // None : put of a new value
+  pub old: Option<Vec<u8>>,
+  // None : the value was deleted
+  pub new: Option<Vec<u8>>,
+}
+
+impl Change {
+  // id , then key / old / new as flag + u32 len + bytes
+  pub(crate) fn encode(&self) -> Vec<u8> {
+    let mut b = (self.id as u64).to_le_bytes().to_vec();
+    for v in [
+      Some(&self.key[..]),
+      self.old.as_deref(),
+      self.new.as_deref(),
+    ] {
+      match v {
+        None => b.push(0),
+        Some(v) => {
+          b.push(1);
+          b.extend((v.len() as u32).to_le_bytes());
+          b.extend(v);
+        }
+      }
+    }
+    b
+  }
+
+  pub(crate) fn decode(b: &[u8]) -> Result<Change, Error> {
+    let err = || Error::Corruption("sdb::Change");
+    let id = u64::from_le_bytes(<[u8; 8]>::try_from(b.get(..8).ok_or_else(err)?).unwrap());
+    let mut b = &b[8..];
+    let mut li = vec![];
+    for _ in 0..3 {
+      let (flag, rest) = b.split_first().ok_or_else(err)?;
+      b = rest;
+      li.push(if *flag == 0 {
+        None
+      } else {
+        let len = u32::from_le_bytes(<[u8; 4]>::try_from(b.get(..4).ok_or_else(err)?).unwrap());
+        let v = b.get(4..4 + len as usize).ok_or_else(err)?;
+        b = &b[4 + len as usize..];
+        Some(v.to_vec())
+      });
+    }
+    let new = li.pop().unwrap();
+    let old = li.pop().unwrap();
+    Ok(Change {
+      id: id as usize,
+      key: li.pop().unwrap().ok_or_else(err)?,
+      old,
+      new,
+    })
+  }
+}
+
+pub(crate) type Filter = Box<dyn Fn(&Change) -> bool + Send + Sync>;
+
+struct Watch {
+  filter: Option<Filter>,
+  sender: Sender<Change>,
+}
+
+pub(crate) struct Feed {
+  // the log tree , see TxArgs::Log
+  log: Option<usize>,
+  watch: Mutex<Vec<Watch>>,
+  // changes of the open write tx , there is at most one
+  pending: Mutex<Vec<Change>>,
+}
+
+impl Feed {
+  pub(crate) fn new(log: Option<usize>) -> Self {
+    Feed {
+      log,
+      watch: Mutex::new(Vec::new()),
+      pending: Mutex::new(Vec::new()),
+    }
+  }
+
+  // the log tree , see TxArgs::Log
+  pub(crate) fn log(&self) -> Option<usize> {
+    self.log
+  }
+
+  // changes are only recorded while they are logged or someone listens
+  pub(crate) fn on(&self) -> bool {
+    self.log.is_some() || !self.watch.lock().unwrap().is_empty()
+  }
+
+  // before commit , in the same write tx : seq -> Change::encode , the last seq is kept in 0
+  pub(crate) fn write_log(&self, txn: &mut MutTxnEnv) -> Result<(), Error> {
+    let id = match self.log {
+      Some(id) => id,
+      None => return Ok(()),
+    };
+    let pending = self.pending.lock().unwrap();
+    if pending.is_empty() {
+      return Ok(());
+    }
+    let mut db = tree::<u64, [u8]>(txn, id)?;
+    let mut seq = last_seq(txn, &db)?;
+    for change in pending.iter() {
+      seq += 1;
+      btree::put(txn, &mut db, &seq, &change.encode()[..])?;
+    }
+    set_seq(txn, &mut db, seq)?;
+    txn.set_root(id, db.db);
+    Ok(())
+  }
+
+  pub(crate) fn push(&self, change: Change) {
+    self.pending.lock().unwrap().push(change);
+  }
+
+  // upsert : the delete of the last old value and the put of the new one are one change
+  pub(crate) fn merge_last(&self) {
+    let mut pending = self.pending.lock().unwrap();
+    let n = pending.len();
+    if n < 2 {
+      return;
+    }
+    let (del, put) = (&pending[n - 2], &pending[n - 1]);
+    if del.id == put.id && del.key == put.key && del.new.is_none() && put.old.is_none() {
+      let put = pending.pop().unwrap();
+      pending[n - 2].new = put.new;
+    }
+  }
+
+  pub(crate) fn subscribe(&self, filter: Option<Filter>) -> Receiver<Change> {
+    let (sender, receiver) = channel();
+    self.watch.lock().unwrap().push(Watch { filter, sender });
+    receiver
+  }
+
+  // after commit
+  pub(crate) fn publish(&self) {
+    let pending = mem::take(&mut *self.pending.lock().unwrap());
+    if pending.is_empty() {
+      return;
+    }
+    let mut watch = self.watch.lock().unwrap();
+    // a dropped receiver unsubscribes
+    watch.retain(|w| {
+      for change in &pending {
+        if w.filter.as_ref().map_or(true, |f| f(change)) && w.sender.send(change.clone()).is_err() {
+          return false;
+        }
+      }
+      true
+    });
+  }
+
+  // after abort
+  pub(crate) fn discard(&self) {
+    self.pending.lock().unwrap().clear();
+  }
+
+  // see Savepoint
+  pub(crate) fn pending_len(&self) -> usize {
+    self.pending.lock().unwrap().len()
+  }
+
+  pub(crate) fn truncate(&self, len: usize) {
+    self.pending.lock().unwrap().truncate(len);
+  }
+}
+
+// the value of key 0 , the last seq of a tree : u64 in the seq trees of Replica and of the parts
+// of a Coordinator , le bytes in the log and the journal whose other values are changes
+pub(crate) trait Seq: UnsizedStorable {
+  fn seq(&self) -> Result<u64, Error>;
+  fn with_seq<R>(seq: u64, f: &mut dyn FnMut(&Self) -> R) -> R;
+}
+
+impl Seq for u64 {
+  fn seq(&self) -> Result<u64, Error> {
+    Ok(*self)
+  }
+
+  fn with_seq<R>(seq: u64, f: &mut dyn FnMut(&Self) -> R) -> R {
+    f(&seq)
+  }
+}
+
+impl Seq for [u8] {
+  fn seq(&self) -> Result<u64, Error> {
+    Ok(u64::from_le_bytes(
+      <[u8; 8]>::try_from(self).map_err(|_| Error::Corruption("sdb seq"))?,
+    ))
+  }
+
+  fn with_seq<R>(seq: u64, f: &mut dyn FnMut(&Self) -> R) -> R {
+    f(&seq.to_le_bytes()[..])
+  }
+}
+
+// 0 for a tree without one
+pub(crate) fn last_seq<T: LoadPage<Error = sanakirja::Error>, V: Seq + ?Sized>(
+  txn: &T,
+  db: &Db_<u64, V, UP<u64, V>>,
+) -> Result<u64, Error> {
+  Ok(match btree::get(txn, db, &0, None)? {
+    Some((&0, v)) => v.seq()?,
+    _ => 0,
+  })
+}
+
+// in the write tx of what seq counts , so that a crash never applies it twice ; set the root of
+// db after it
+pub(crate) fn set_seq<V: Seq + ?Sized>(
+  txn: &mut MutTxnEnv,
+  db: &mut Db_<u64, V, UP<u64, V>>,
+  seq: u64,
+) -> Result<(), Error> {
+  btree::del(txn, db, &0, None)?;
+  V::with_seq(seq, &mut |v| btree::put(txn, db, &0, v))?;
+  Ok(())
+}
+
+impl Tx {
+  // the changes of all trees , sent after each commit
+  pub fn subscribe(&self) -> Receiver<Change> {
+    self.feed.subscribe(None)
+  }
+
+  fn log_id(&self) -> Result<usize, Error> {
+    self.feed.log.ok_or(Error::NoLog)
+  }
+
+  // the logged changes with seq > since , see TxArgs::Log
+  pub fn changes_since(&self, since: u64) -> Result<Vec<(u64, Change)>, Error> {
+    let id = self.log_id()?;
+    let txn = Env::txn_begin(&self.env)?;
+    let mut li = vec![];
+    if let Some(db) = txn.root_db::<u64, [u8], UP<u64, [u8]>>(id) {
+      for entry in btree::iter(&txn, &db, Some((&since.saturating_add(1).max(1), None)))? {
+        let (seq, v) = entry?;
+        li.push((*seq, Change::decode(v)?));
+      }
+    }
+    Ok(li)
+  }
+
+  // the seq of the last logged change , 0 if none
+  pub fn log_seq(&self) -> Result<u64, Error> {
+    let id = self.log_id()?;
+    let txn = Env::txn_begin(&self.env)?;
+    match txn.root_db::<u64, [u8], UP<u64, [u8]>>(id) {
+      Some(db) => last_seq(&txn, &db),
+      None => Ok(0),
+    }
+  }
+
+  // delete the logged changes with seq <= upto , once every consumer has them
+  pub fn truncate_log(&self, upto: u64) -> Result<usize, Error> {
+    let id = self.log_id()?;
+    let w = self.w()?;
+    let txn = unsafe { &mut *(w.ptr() as *mut MutTxnEnv) };
+    // the write tx commits on drop : a failed delete is aborted , not committed half done
+    let deleted = (|| -> Result<usize, Error> {
+      let mut n = 0;
+      if let Some(mut db) = txn.root_db::<u64, [u8], UP<u64, [u8]>>(id) {
+        loop {
+          let seq = match btree::iter(txn, &db, Some((&1, None)))?.next() {
+            Some(entry) => *entry?.0,
+            None => break,
+          };
+          if seq > upto {
+            break;
+          }
+          btree::del(txn, &mut db, &seq, None)?;
+          n += 1;
+        }
+        txn.set_root(id, db.db);
+      }
+      Ok(n)
+    })();
+    match deleted {
+      Ok(n) => {
+        w.commit()?;
+        Ok(n)
+      }
+      Err(err) => {
+        w.abort();
+        Err(err)
+      }
+    }
+  }
+}
@@ -0,0 +1,76 @@
+use crate::stats::ROOTS;
+use crate::tx::TxnEnv;
+use crate::walk::{walk, PAGE_SIZE};
+use crate::Error;
+use sanakirja::btree::{self, page::Page, Db_};
+use sanakirja::{LoadPage, L64};
+use std::convert::TryFrom;
+
+// each root page starts with the header of sanakirja : version u16 , root u8 , n_roots u8 ,
+// crc u32 , length u64 , free_db u64 , rc_db u64 ; the roots follow . This is the layout of
+// sanakirja 1.2.5 , the version Cargo.toml pins
+pub(crate) const HEADER: usize = 32;
+
+// the allocator state of a read snapshot
+pub(crate) struct FreeList {
+  // end of the allocated pages , the rest of the file is free too
+  pub length: u64,
+  // free pages below length , sorted offsets
+  pub pages: Vec<u64>,
+  // a page of the free list beyond the end of the file (truncated) : pages is then empty
+  pub outside: Option<u64>,
+}
+
+impl FreeList {
+  // pages a write tx can take without growing the file
+  pub(crate) fn count(&self, file: u64) -> u64 {
+    self.pages.len() as u64 + file.saturating_sub(self.length) / PAGE_SIZE
+  }
+}
+
+fn u64_at(b: &[u8], at: usize) -> u64 {
+  u64::from_le_bytes(<[u8; 8]>::try_from(&b[at..at + 8]).unwrap())
+}
+
+// the root page of the snapshot is the one that holds the roots of txn ; file is the length of
+// the file , a page beyond it is not loaded
+pub(crate) fn free_list(txn: &TxnEnv, max_tx: usize, file: u64) -> Result<FreeList, Error> {
+  let mut header = None;
+  for i in 0..max_tx as u64 {
+    let page = txn.load_page(i * PAGE_SIZE)?;
+    let b = unsafe { std::slice::from_raw_parts(page.data, PAGE_SIZE as usize) };
+    if (0..ROOTS).all(|n| txn.root(n).unwrap_or(0) == u64_at(b, HEADER + 8 * n)) {
+      header = Some((u64_at(b, 8), u64_at(b, 16)));
+      break;
+    }
+  }
+  let (length, free_db) = header.ok_or(Error::Corruption("sdb root page"))?;
+  let mut pages = vec![];
+  let mut outside = None;
+  if free_db != 0 {
+    let out = |offset: u64| (offset & !(PAGE_SIZE - 1)) + PAGE_SIZE > file;
+    let db = Db_::<L64, (), Page<L64, ()>>::from_page(free_db);
+    if out(free_db) {
+      outside = Some(free_db & !(PAGE_SIZE - 1));
+    } else {
+      walk(txn, &db, &mut |page| {
+        if let Some(child) = page.children.iter().find(|child| out(**child)) {
+          outside = Some(child & !(PAGE_SIZE - 1));
+        }
+        outside.is_none()
+      })?;
+    }
+    if outside.is_none() {
+      for entry in btree::iter(txn, &db, None)? {
+        let (offset, _) = entry?;
+        pages.push(u64::from(*offset) & !(PAGE_SIZE - 1));
+      }
+    }
+  }
+  pages.sort_unstable();
+  Ok(FreeList {
+    length,
+    pages,
+    outside,
+  })
+}
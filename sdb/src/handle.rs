@@ -0,0 +1,71 @@
+use crate::{DbPage, Encode, Page, Tx, UP};
+use sanakirja::btree::{BTreeMutPage, BTreePage};
+use sanakirja::Storable;
+use std::sync::Arc;
+
+// a DbPage that owns its Tx , for databases opened at run time instead of static ones ; the Tx
+// is closed when its last handle drops
+pub struct DbHandle<
+  K: ?Sized + Storable + PartialEq,
+  V: ?Sized + Storable + PartialEq,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+  RK: ?Sized + Encode<K>,
+  RV: ?Sized + Encode<V>,
+> {
+  // borrows tx , declared first so that it is dropped first
+  page: DbPage<'static, K, V, P, RK, RV>,
+  tx: Arc<Tx>,
+}
+
+pub type Handle<K, V> = DbHandle<K, V, Page<K, V>, K, V>;
+pub type HandleU<K, V> = DbHandle<K, V, UP<K, V>, K, V>;
+
+impl<
+    K: ?Sized + Storable + PartialEq,
+    V: ?Sized + Storable + PartialEq,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  > DbHandle<K, V, P, RK, RV>
+{
+  // e.g. DbHandle::new(tx, |tx| tx.db(1).with_index("name", 2, |v: &User| v.name))
+  pub fn new<F>(tx: Arc<Tx>, f: F) -> Self
+  where
+    F: for<'x> FnOnce(&'x Tx) -> DbPage<'x, K, V, P, RK, RV>,
+  {
+    // the Tx lives in the Arc as long as the handle , and f cannot keep the reference
+    let page = f(unsafe { &*Arc::as_ptr(&tx) });
+    DbHandle { page, tx }
+  }
+
+  // borrowed from the handle , not for 'static
+  pub fn page(&self) -> &DbPage<'_, K, V, P, RK, RV> {
+    &self.page
+  }
+
+  pub fn tx(&self) -> &Arc<Tx> {
+    &self.tx
+  }
+}
+
+impl<
+    K: ?Sized + Storable + PartialEq,
+    V: ?Sized + Storable + PartialEq,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  > Clone for DbHandle<K, V, P, RK, RV>
+{
+  fn clone(&self) -> Self {
+    DbHandle {
+      page: DbPage {
+        tx: self.page.tx,
+        id: self.page.id,
+        index: self.page.index.clone(),
+        ttl: self.page.ttl,
+        _kvp: self.page._kvp,
+      },
+      tx: self.tx.clone(),
+    }
+  }
+}
@@ -0,0 +1,139 @@
+use crate::tx::MutTxnEnv;
+use crate::{tree, Error};
+use sanakirja::btree;
+use sanakirja::UnsizedStorable;
+use std::sync::Arc;
+
+// secondary index declared by DbPage::with_index , index key -> primary key in the tree `id`
+pub struct Index<V: ?Sized> {
+  pub name: String,
+  pub id: usize,
+  pub unique: bool,
+  pub(crate) key: Arc<dyn Fn(&V) -> Vec<u8> + Send + Sync>,
+}
+
+impl<V: ?Sized> Clone for Index<V> {
+  fn clone(&self) -> Self {
+    Index {
+      name: self.name.clone(),
+      id: self.id,
+      unique: self.unique,
+      key: self.key.clone(),
+    }
+  }
+}
+
+pub(crate) fn index_put<K: UnsizedStorable + ?Sized>(
+  tx: &mut MutTxnEnv,
+  id: usize,
+  ik: &[u8],
+  k: &K,
+) -> Result<bool, Error> {
+  let mut db = tree::<[u8], K>(tx, id)?;
+  let r = btree::put(tx, &mut db, ik, k)?;
+  tx.set_root(id, db.db);
+  Ok(r)
+}
+
+pub(crate) fn index_del<K: UnsizedStorable + ?Sized>(
+  tx: &mut MutTxnEnv,
+  id: usize,
+  ik: &[u8],
+  k: &K,
+) -> Result<bool, Error> {
+  let mut db = tree::<[u8], K>(tx, id)?;
+  let r = btree::del(tx, &mut db, ik, Some(k))?;
+  tx.set_root(id, db.db);
+  Ok(r)
+}
+
+// bytes whose order is the order of the value , so that index trees can be scanned by range
+pub trait IndexKey {
+  fn index_key(&self) -> Vec<u8>;
+}
+
+macro_rules! index_key_unsigned {
+  ( $( $x:ty ),* ) => {
+    $(impl IndexKey for $x {
+      #[inline]
+      fn index_key(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+      }
+    })*
+  };
+}
+
+index_key_unsigned!(u8, u16, u32, u64, u128, usize);
+
+// flip the sign bit so that negative numbers sort first
+macro_rules! index_key_signed {
+  ( $( $x:ty : $u:ty ),* ) => {
+    $(impl IndexKey for $x {
+      #[inline]
+      fn index_key(&self) -> Vec<u8> {
+        ((*self as $u) ^ (1 << (<$u>::BITS - 1))).to_be_bytes().to_vec()
+      }
+    })*
+  };
+}
+
+index_key_signed!(i8: u8, i16: u16, i32: u32, i64: u64, i128: u128, isize: usize);
+
+impl IndexKey for bool {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    vec![*self as u8]
+  }
+}
+
+impl IndexKey for [u8] {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    self.to_vec()
+  }
+}
+
+impl<const N: usize> IndexKey for [u8; N] {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    self.to_vec()
+  }
+}
+
+impl IndexKey for Vec<u8> {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    self.clone()
+  }
+}
+
+impl IndexKey for str {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    self.as_bytes().to_vec()
+  }
+}
+
+impl IndexKey for String {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    self.as_bytes().to_vec()
+  }
+}
+
+impl<T: IndexKey + ?Sized> IndexKey for &T {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    (**self).index_key()
+  }
+}
+
+// fixed size A first , e.g. (created_at, id)
+impl<A: IndexKey, B: IndexKey> IndexKey for (A, B) {
+  #[inline]
+  fn index_key(&self) -> Vec<u8> {
+    let mut key = self.0.index_key();
+    key.extend(self.1.index_key());
+    key
+  }
+}
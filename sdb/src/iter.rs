@@ -1,3 +1,5 @@
+use crate::verify::{verify, Verify};
+use crate::Error;
 use sanakirja::btree::{BTreePage, Cursor, Db_};
 use sanakirja::{LoadPage, Storable};
 use std::marker::PhantomData;
@@ -78,3 +80,36 @@ impl<
     }
   }
 }
+
+// k -> is expired , see DbPage::with_ttl
+pub(crate) type Expired<'a, K> = Box<dyn Fn(&K) -> Result<bool, Error> + 'a>;
+
+// check every entry read from the pages , see Verify , and skip the expired ones ; None for a
+// tree missing from the file of a read only Tx
+pub struct Verified<'a, I, K: ?Sized>(pub(crate) Option<I>, pub(crate) Option<Expired<'a, K>>);
+
+impl<'a, I, K, V> Iterator for Verified<'a, I, K>
+where
+  I: Iterator<Item = Result<(&'a K, &'a V), sanakirja::Error>>,
+  K: Verify + ?Sized + 'a,
+  V: Verify + ?Sized + 'a,
+{
+  type Item = Result<(&'a K, &'a V), Error>;
+  #[inline]
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let (k, v) = match self.0.as_mut()?.next()? {
+        Ok(kv) => kv,
+        Err(err) => return Some(Err(err.into())),
+      };
+      if let Some(expired) = &self.1 {
+        match expired(k) {
+          Ok(true) => continue,
+          Ok(false) => {}
+          Err(err) => return Some(Err(err)),
+        }
+      }
+      return Some(verify(k).and_then(|_| verify(v)).map(|_| (k, v)));
+    }
+  }
+}
@@ -4,30 +4,131 @@ pub use dbpage::{DbPage, Encode};
 mod tx;
 pub use tx::{MutTxnEnv, Tx, TxnEnv};
 mod iter;
-use iter::key_iter;
+pub use iter::Verified;
+use iter::{key_iter, Expired};
+mod error;
+pub use error::Error;
+mod verify;
+use verify::verify;
+pub use verify::Verify;
+mod view;
+pub use view::{view, view_slice, View};
+mod index;
+use index::{index_del, index_put};
+pub use index::{Index, IndexKey};
+mod owned;
+use owned::bytes;
+pub use owned::Owned;
+mod backup;
+mod export;
+pub use export::{Codec, Format};
+mod compact;
+pub use compact::Space;
+mod registry;
+mod replica;
+mod stats;
+mod check;
+pub use check::{Problem, Report};
+mod walk;
+mod dot;
+mod handle;
+pub use handle::{DbHandle, Handle, HandleU};
+mod shard;
+pub use shard::ShardedTx;
+mod twophase;
+pub use twophase::{Coordinator, Intent};
+mod savepoint;
+pub use savepoint::Savepoint;
+mod free;
+mod size;
+mod temp;
+pub use replica::{read_changes, write_changes, Replica};
+pub use stats::{Stats, TreeStats};
+mod feed;
+pub use feed::Change;
+use feed::Feed;
+mod ttl;
+use ttl::{deadline, now, ttl_clear, ttl_expired, ttl_set};
+pub use ttl::{Expirer, Ttl};
 
 extern crate sdb_macro;
 pub use sdb_macro::Sdb;
 
 pub use sanakirja::btree::page::Page;
 use sanakirja::btree::{create_db_, BTreeMutPage, BTreePage, Db_, Iter, RevIter};
-pub use sanakirja::{btree, direct_repr, Commit, Error, Storable, UnsizedStorable};
+pub use sanakirja::{btree, direct_repr, Commit, Storable, UnsizedStorable};
 use sanakirja::{Env, LoadPage, RootDb};
+use std::borrow::Cow;
 use std::convert::Into;
-use std::fs::create_dir_all;
+use std::fs::{create_dir_all, metadata};
 use std::marker::PhantomData;
-use std::mem;
 use std::mem::ManuallyDrop;
 use std::path::PathBuf;
 use std::result::Result;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
 #[macro_export]
-pub macro repr($cls:ident) {
-  use sdb::direct_repr;
-  direct_repr!($cls);
+pub macro repr {
+  ($cls:ident) => {
+    repr!($cls, verify |_v| true);
+  },
+  ($cls:ident, |$a:ident, $b:ident| $cmp:expr) => {
+    repr!($cls, |$a, $b| $cmp, verify |_v| true);
+  },
+  ($cls:ident, verify |$v:ident| $verify:expr) => {
+    use sdb::direct_repr;
+    direct_repr!($cls);
 
-  use sdb::encode;
-  encode!($cls);
+    repr!(@encode $cls, |$v| $verify);
+  },
+  // same as direct_repr!, but the tree is ordered by $cmp instead of Ord
+  ($cls:ident, |$a:ident, $b:ident| $cmp:expr, verify |$v:ident| $verify:expr) => {
+    impl sdb::Storable for $cls {
+      type PageReferences = core::iter::Empty<u64>;
+      fn page_references(&self) -> Self::PageReferences {
+        core::iter::empty()
+      }
+      fn compare<T>(&self, _: &T, $b: &Self) -> core::cmp::Ordering {
+        let $a = self;
+        $cmp
+      }
+    }
+
+    impl sdb::UnsizedStorable for $cls {
+      const ALIGN: usize = core::mem::align_of::<$cls>();
+
+      fn size(&self) -> usize {
+        core::mem::size_of::<Self>()
+      }
+
+      unsafe fn onpage_size(_: *const u8) -> usize {
+        core::mem::size_of::<Self>()
+      }
+
+      unsafe fn write_to_page(&self, p: *mut u8) {
+        core::ptr::copy_nonoverlapping(self, p as *mut Self, 1)
+      }
+
+      unsafe fn from_raw_ptr<'a, T>(_: &T, p: *const u8) -> &'a Self {
+        &*(p as *const Self)
+      }
+    }
+
+    repr!(@encode $cls, |$v| $verify);
+  },
+  (@encode $cls:ident, |$v:ident| $verify:expr) => {
+    use sdb::encode;
+    encode!($cls);
+
+    impl sdb::Verify for $cls {
+      #[inline]
+      fn verify(&self) -> bool {
+        let $v = self;
+        $verify
+      }
+    }
+  },
 }
 
 pub struct TxDb<
@@ -40,43 +141,106 @@ pub struct TxDb<
   RV: ?Sized + Encode<V>,
 > {
   db: Db_<K, V, P>,
+  // the tree is missing from the file of a read only Tx : reads find nothing and db , a
+  // placeholder , is never loaded
+  empty: bool,
+  // Tx::rollbacks when db was read , see TxDb::root
+  gen: u64,
   id: usize,
   tx: *mut T,
+  index: &'a [Index<V>],
+  ttl: Option<Ttl>,
+  owner: *const Tx,
   pub(crate) _rkv: PhantomData<(&'a RK, &'a RV)>,
 }
 
 type UP<K, V> = btree::page_unsized::Page<K, V>;
 
+// the tree `id` , created if missing
+pub(crate) fn tree<K: UnsizedStorable + ?Sized, V: UnsizedStorable + ?Sized>(
+  tx: &mut MutTxnEnv,
+  id: usize,
+) -> Result<Db_<K, V, UP<K, V>>, Error> {
+  Ok(match tx.root_db::<K, V, UP<K, V>>(id) {
+    Some(db) => db,
+    None => {
+      let db = create_db_::<_, K, V, UP<K, V>>(tx)?;
+      tx.set_root(id, db.db);
+      db
+    }
+  })
+}
+
 pub type Db<'a, K, V> = DbPage<'a, K, V, Page<K, V>, K, V>;
 pub type DbU<'a, K, V> = DbPage<'a, K, V, UP<K, V>, K, V>;
 pub type DbEk<'a, K, V, RK> = DbPage<'a, K, V, UP<K, V>, RK, V>;
 pub type DbEv<'a, K, V, RV> = DbPage<'a, K, V, UP<K, V>, K, RV>;
 pub type DbEkv<'a, K, V, RK, RV> = DbPage<'a, K, V, UP<K, V>, RK, RV>;
 
-pub struct WriteTx<'a>(ManuallyDrop<MutTxnEnv<'a>>);
+// commit on drop , the changes are then sent to the watchers , see Tx::subscribe
+pub struct WriteTx<'a> {
+  txn: ManuallyDrop<MutTxnEnv<'a>>,
+  tx: &'a Tx,
+}
 pub struct ReadTx<'a>(TxnEnv<'a>);
 
+#[cfg(feature = "desse")]
+pub use desse;
+
+// the on-disk type of a desse! type , see DbDesse
+#[cfg(feature = "desse")]
+pub trait DesseRepr {
+  type Repr;
+}
+
+#[cfg(feature = "desse")]
+pub type DbDesse<'a, K, V> = DbEv<'a, K, <V as DesseRepr>::Repr, V>;
+#[cfg(feature = "desse")]
+pub type DbDesseK<'a, K, V> = DbEk<'a, <K as DesseRepr>::Repr, V, K>;
+#[cfg(feature = "desse")]
+pub type DbDesseKv<'a, K, V> = DbEkv<'a, <K as DesseRepr>::Repr, <V as DesseRepr>::Repr, K, V>;
+
 #[cfg(feature = "desse")]
 #[macro_export]
 macro_rules! desse {
   ($cls:ident, $desse:ident) => {
     #[derive(
-      Default, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, Debug, DesseSized, Desse,
+      Eq,
+      PartialEq,
+      PartialOrd,
+      Ord,
+      Hash,
+      Clone,
+      Copy,
+      Debug,
+      $crate::desse::DesseSized,
+      $crate::desse::Desse,
     )]
-    pub struct $desse([u8; $cls::SIZE]);
+    pub struct $desse([u8; <$cls as $crate::desse::DesseSized>::SIZE]);
+
+    // not derived : [u8; N] is only Default up to N = 32
+    impl Default for $desse {
+      fn default() -> Self {
+        $desse([0; <$cls as $crate::desse::DesseSized>::SIZE])
+      }
+    }
 
-    sdb::direct_repr!($desse);
+    $crate::repr!($desse);
 
-    impl Encode<$desse> for $cls {
+    impl $crate::DesseRepr for $cls {
+      type Repr = $desse;
+    }
+
+    impl $crate::Encode<$desse> for $cls {
       #[inline]
       fn encode<R: Sized>(&self, next: &mut dyn FnMut(&$desse) -> R) -> R {
-        next(&$desse(self.serialize()))
+        next(&$desse($crate::desse::Desse::serialize(self)))
       }
     }
 
     impl From<&$desse> for $cls {
       fn from(v: &$desse) -> Self {
-        Data2::deserialize_from(&v.0)
+        <$cls as $crate::desse::Desse>::deserialize_from(&v.0)
       }
     }
   };
@@ -85,20 +249,31 @@ macro_rules! desse {
 macro_rules! tx {
   ($cls:ident, $tx:tt) => {
     impl<'a> $cls<'a> {
+      // the TxDb borrows the index of db , so it can not outlive it
       pub fn db<
+        'd,
         K: ?Sized + Storable + PartialEq,
         V: ?Sized + Storable + PartialEq,
         P: BTreeMutPage<K, V> + BTreePage<K, V>,
         RK: ?Sized + Encode<K>,
         RV: ?Sized + Encode<V>,
       >(
-        &self,
-        db: &DbPage<K, V, P, RK, RV>,
-      ) -> TxDb<K, V, $tx<'a>, P, RK, RV> {
+        &'d self,
+        db: &'d DbPage<K, V, P, RK, RV>,
+      ) -> TxDb<'d, K, V, $tx<'a>, P, RK, RV> {
+        let (tree, empty) = match self.root(db.id) {
+          Some(tree) => (tree, false),
+          None => (Db_::from_page(0), true),
+        };
         TxDb {
           id: db.id,
-          db: self.btree(db.id),
+          db: tree,
+          empty,
+          gen: db.tx.rollbacks.load(Ordering::Relaxed),
           tx: self.ptr() as *mut $tx,
+          index: &db.index[..],
+          ttl: db.ttl,
+          owner: db.tx,
           _rkv: PhantomData {},
         }
       }
@@ -121,14 +296,46 @@ impl<'a> ReadTx<'a> {
     &self,
     id: usize,
   ) -> Db_<K, V, P> {
-    let tx = &self.0;
-    tx.root_db::<K, V, P>(id).unwrap()
+    self
+      .root(id)
+      .unwrap_or_else(|| panic!("no tree {} in the file of a read only Tx", id))
+  }
+
+  // None for a tree missing from the file , see TxArgs::ReadOnly
+  fn root<K: Storable + ?Sized, V: Storable + ?Sized, P: BTreeMutPage<K, V> + BTreePage<K, V>>(
+    &self,
+    id: usize,
+  ) -> Option<Db_<K, V, P>> {
+    self.0.root_db::<K, V, P>(id)
   }
 }
 
 impl<'a> WriteTx<'a> {
   pub fn ptr(&self) -> *const MutTxnEnv<'a> {
-    &*self.0
+    &*self.txn
+  }
+
+  pub fn commit(self) -> Result<(), Error> {
+    let mut this = ManuallyDrop::new(self);
+    let txn = unsafe { ManuallyDrop::take(&mut this.txn) };
+    // the changes of a failed commit are neither logged nor sent with the next one
+    match commit(this.tx, txn) {
+      Ok(()) => {
+        this.tx.feed.publish();
+        Ok(())
+      }
+      Err(err) => {
+        this.tx.feed.discard();
+        Err(err)
+      }
+    }
+  }
+
+  // nothing is written and no change is sent
+  pub fn abort(self) {
+    let mut this = ManuallyDrop::new(self);
+    unsafe { ManuallyDrop::drop(&mut this.txn) };
+    this.tx.feed.discard();
   }
 
   pub fn btree<
@@ -151,13 +358,36 @@ impl<'a> WriteTx<'a> {
       Some(tree) => tree,
     }
   }
+
+  // a write tx creates the missing trees
+  fn root<K: Storable + ?Sized, V: Storable + ?Sized, P: BTreeMutPage<K, V> + BTreePage<K, V>>(
+    &self,
+    id: usize,
+  ) -> Option<Db_<K, V, P>> {
+    Some(self.btree(id))
+  }
+}
+
+// the txn is dropped , so aborted , on error
+fn commit(tx: &Tx, mut txn: MutTxnEnv) -> Result<(), Error> {
+  tx.feed.write_log(&mut txn)?;
+  tx.fits()?;
+  txn.commit()?;
+  Ok(())
 }
 
 impl<'a> Drop for WriteTx<'a> {
   fn drop(&mut self) {
-    let tx = mem::MaybeUninit::<MutTxnEnv>::uninit();
-    let tx = mem::replace(&mut *self.0, unsafe { tx.assume_init() });
-    tx.commit().unwrap();
+    let txn = unsafe { ManuallyDrop::take(&mut self.txn) };
+    // no panic in drop : on error (e.g. Error::Full) nothing is committed , see
+    // Tx::take_drop_error
+    match commit(self.tx, txn) {
+      Ok(()) => self.tx.feed.publish(),
+      Err(err) => {
+        self.tx.feed.discard();
+        *self.tx.drop_error.lock().unwrap() = Some(err);
+      }
+    }
   }
 }
 
@@ -180,38 +410,112 @@ macro_rules! iter {
       &self,
       k: OptionK,
       v: OptionV,
-    ) -> Result<$cls<'a, T, K, V, P>, <T as LoadPage>::Error> {
-      match k.into() {
+    ) -> Result<Verified<'a, $cls<'a, T, K, V, P>, K>, Error> {
+      let db = match self.root() {
+        Some(db) => db,
+        None => return Ok(Verified(None, None)),
+      };
+      Ok(self.verified(match k.into() {
         None => {
           let tx = unsafe { &*self.tx };
-          $real(tx, &self.db, None)
+          $real(tx, &db, None)
         }
         Some(k) => match v.into() {
           Some(v) => {
             encode_k_v!(k, v, {
               let tx = unsafe { &*self.tx };
-              $real(tx, &self.db, Some((k, Some(v))))
+              $real(tx, &db, Some((k, Some(v))))
             })
           }
           None => {
             encode!(k, {
               let tx = unsafe { &*self.tx };
-              $real(tx, &self.db, Some((k, None)))
+              $real(tx, &db, Some((k, None)))
             })
           }
         },
-      }
+      }?))
     }
   };
 }
 
+impl<
+    'b,
+    K: Storable + PartialEq + ?Sized,
+    V: Storable + PartialEq + ?Sized,
+    T: LoadPage,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  > TxDb<'b, K, V, T, P, RK, RV>
+{
+  #[inline]
+  fn index(&self) -> &'b [Index<V>] {
+    self.index
+  }
+
+  fn find_index(&self, name: &str) -> Result<&'b Index<V>, Error> {
+    match self.index().iter().find(|i| i.name == name) {
+      Some(index) => Ok(index),
+      None => Err(Error::NoIndex(name.into())),
+    }
+  }
+}
+
+// ttl , see DbPage::with_ttl
+impl<
+    'a,
+    'b,
+    K: 'a + Storable + UnsizedStorable + PartialEq + ?Sized,
+    V: 'a + Storable + PartialEq + ?Sized,
+    T: 'a + LoadPage<Error = sanakirja::Error> + RootDb,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  > TxDb<'b, K, V, T, P, RK, RV>
+{
+  // None : no tree ; read again from the txn once a Savepoint::rollback has moved or removed it
+  fn root(&self) -> Option<Db_<K, V, P>> {
+    if self.gen != unsafe { &*self.owner }.rollbacks.load(Ordering::Relaxed) {
+      let tx: &T = unsafe { &*self.tx };
+      return tx.root_db::<K, V, P>(self.id);
+    }
+    if self.empty {
+      None
+    } else {
+      Some(Db_::from_page(self.db.db))
+    }
+  }
+
+  // k -> is expired , None without ttl
+  fn expired(&self) -> Option<Expired<'a, K>> {
+    let ttl = self.ttl?;
+    let tx: &'a T = unsafe { &*self.tx };
+    let db = tx.root_db::<K, u64, UP<K, u64>>(ttl.id)?;
+    let now = now();
+    Some(Box::new(move |k| ttl_expired(tx, &db, k, now)))
+  }
+
+  fn is_expired(&self, k: &K) -> Result<bool, Error> {
+    match self.expired() {
+      Some(expired) => expired(k),
+      None => Ok(false),
+    }
+  }
+
+  #[inline]
+  fn verified<I>(&self, iter: I) -> Verified<'a, I, K> {
+    Verified(Some(iter), self.expired())
+  }
+}
+
 // all TxDb
 impl<
     'a,
     'b,
-    K: 'a + PartialEq + Storable + ?Sized,
-    V: 'a + PartialEq + Storable + ?Sized,
-    T: 'a + LoadPage,
+    K: 'a + PartialEq + Storable + UnsizedStorable + Verify + ?Sized,
+    V: 'a + PartialEq + Storable + Verify + ?Sized,
+    T: 'a + LoadPage<Error = sanakirja::Error> + RootDb,
     P: 'a + BTreeMutPage<K, V> + BTreePage<K, V>,
     RK: 'a + ?Sized + Encode<K>,
     RV: 'a + ?Sized + Encode<V>,
@@ -224,21 +528,31 @@ impl<
   pub fn key_iter<'c>(
     &self,
     k: &'a RK,
-  ) -> Result<Box<dyn Iterator<Item = Result<(&'a K, &'a V), T::Error>> + 'a>, T::Error> {
+  ) -> Result<Box<dyn Iterator<Item = Result<(&'a K, &'a V), Error>> + 'a>, Error> {
+    let db = match self.root() {
+      Some(db) => db,
+      None => return Ok(Box::new(std::iter::empty())),
+    };
     let tx = unsafe { &*self.tx };
-    encode!(k, key_iter(tx, &self.db, k))
+    Ok(Box::new(self.verified(encode!(k, key_iter(tx, &db, k))?)))
   }
 
   #[inline]
-  pub fn exist(&self, k: &RK, v: &RV) -> Result<bool, <T as LoadPage>::Error> {
+  pub fn exist(&self, k: &RK, v: &RV) -> Result<bool, Error> {
+    let db = match self.root() {
+      Some(db) => db,
+      None => return Ok(false),
+    };
     encode_k_v!(k, v, {
       let tx = unsafe { &*self.tx };
-      match btree::get(tx, &self.db, k, Some(v))? {
+      match btree::get(tx, &db, k, Some(v))? {
         None => Ok(false),
         Some((key, val)) => {
+          verify(key)?;
+          verify(val)?;
           if key == k {
             if val == v {
-              Ok(true)
+              Ok(!self.is_expired(k)?)
             } else {
               Ok(false)
             }
@@ -250,14 +564,49 @@ impl<
     })
   }
 
+  // one() decoded into RV , e.g. for desse! types
   #[inline]
-  pub fn one(&self, k: &RK) -> Result<Option<&'a V>, <T as LoadPage>::Error> {
+  pub fn get(&self, k: &RK) -> Result<Option<RV>, Error>
+  where
+    RV: Sized + for<'x> From<&'x V>,
+  {
+    Ok(self.one(k)?.map(RV::from))
+  }
+
+  // all values of the key , decoded into RV
+  #[inline]
+  pub fn get_all(&self, k: &RK) -> Result<Vec<RV>, Error>
+  where
+    RV: Sized + for<'x> From<&'x V>,
+  {
+    let db = match self.root() {
+      Some(db) => db,
+      None => return Ok(vec![]),
+    };
+    let tx = unsafe { &*self.tx };
+    encode!(k, {
+      let mut li = vec![];
+      for entry in self.verified(key_iter(tx, &db, k)?) {
+        li.push(RV::from(entry?.1));
+      }
+      Ok(li)
+    })
+  }
+
+  #[inline]
+  pub fn one(&self, k: &RK) -> Result<Option<&'a V>, Error> {
+    let db = match self.root() {
+      Some(db) => db,
+      None => return Ok(None),
+    };
     encode!(k, {
       let tx = unsafe { &*self.tx };
-      match btree::get(tx, &self.db, k, None)? {
+      match btree::get(tx, &db, k, None)? {
         None => Ok(None),
         Some((key, v)) => {
-          if key == k {
+          verify(key)?;
+          if key == k && !self.is_expired(k)? {
+            verify(v)?;
             Ok(Some(v))
           } else {
             Ok(None)
@@ -266,6 +615,76 @@ impl<
       }
     })
   }
+
+  // secondary index lookup , see DbPage::with_index
+  pub fn by_index<IK: IndexKey + ?Sized>(
+    &self,
+    name: &str,
+    ik: &IK,
+  ) -> Result<Vec<(&'a K, &'a V)>, Error> {
+    let index = self.find_index(name)?;
+    let ik = ik.index_key();
+    let tx = unsafe { &*self.tx };
+    let mut li = vec![];
+    let primary = match self.root() {
+      Some(db) => db,
+      None => return Ok(li),
+    };
+    if let Some(db) = tx.root_db::<[u8], K, UP<[u8], K>>(index.id) {
+      for entry in key_iter(tx, &db, &ik[..])? {
+        let (_, k) = entry?;
+        for entry in self.verified(key_iter(tx, &primary, k)?) {
+          let (k, v) = entry?;
+          if (index.key)(v) == ik {
+            li.push((k, v));
+          }
+        }
+      }
+    }
+    Ok(li)
+  }
+}
+
+// [u8] values read as typed views over the page memory , see View
+impl<
+    'a,
+    'b,
+    K: 'a + PartialEq + Storable + UnsizedStorable + Verify + ?Sized,
+    T: 'a + LoadPage<Error = sanakirja::Error> + RootDb,
+    P: 'a + BTreeMutPage<K, [u8]> + BTreePage<K, [u8]>,
+    RK: 'a + ?Sized + Encode<K>,
+    RV: 'a + ?Sized + Encode<[u8]>,
+  > TxDb<'b, K, [u8], T, P, RK, RV>
+{
+  #[inline]
+  pub fn one_view<X: View>(&self, k: &RK) -> Result<Option<Cow<'a, X>>, Error> {
+    match self.one(k)? {
+      None => Ok(None),
+      Some(v) => Ok(Some(view(v)?)),
+    }
+  }
+
+  #[inline]
+  pub fn iter_view<X: View, OptionK: Into<Option<&'a RK>>>(
+    &self,
+    k: OptionK,
+  ) -> Result<impl Iterator<Item = Result<(&'a K, Cow<'a, X>), Error>>, Error> {
+    Ok(self.iter(k, None::<&'a RV>)?.map(|entry| {
+      let (k, v) = entry?;
+      Ok((k, view(v)?))
+    }))
+  }
+
+  #[inline]
+  pub fn riter_view<X: View, OptionK: Into<Option<&'a RK>>>(
+    &self,
+    k: OptionK,
+  ) -> Result<impl Iterator<Item = Result<(&'a K, Cow<'a, X>), Error>>, Error> {
+    Ok(self.riter(k, None::<&'a RV>)?.map(|entry| {
+      let (k, v) = entry?;
+      Ok((k, view(v)?))
+    }))
+  }
 }
 
 macro_rules! set_root {
@@ -285,8 +704,8 @@ macro_rules! set_root {
 impl<
     'a,
     'b,
-    K: 'a + Storable + PartialEq + ?Sized,
-    V: 'a + Storable + PartialEq + ?Sized,
+    K: 'a + Storable + UnsizedStorable + PartialEq + ?Sized,
+    V: 'a + Storable + UnsizedStorable + PartialEq + ?Sized,
     P: BTreeMutPage<K, V> + BTreePage<K, V>,
     RK: ?Sized + Encode<K>,
     RV: ?Sized + Encode<V>,
@@ -294,55 +713,305 @@ impl<
 {
   #[inline]
   pub fn put(&mut self, k: &RK, v: &RV) -> std::result::Result<bool, Error> {
-    encode_k_v!(
-      k,
-      v,
-      set_root!(btree::put(tx, &mut self.db, k, v), self, tx)
-    )
+    encode_k_v!(k, v, self.put_raw(k, v))
+  }
+
+  // put of an encoded key and value , e.g. DbPage::import
+  pub(crate) fn put_raw(&mut self, k: &K, v: &V) -> Result<bool, Error> {
+    self.purge(k)?;
+    self.check_unique(k, v, false)?;
+    self.put_kv(k, v)
+  }
+
+  // put , then k and all its values expire after ttl , see DbPage::with_ttl ; on error abort the
+  // write tx , the value may be written without its deadline
+  pub fn put_with_ttl(&mut self, k: &RK, v: &RV, ttl: Duration) -> Result<bool, Error> {
+    let ttl_db = match self.ttl {
+      Some(ttl_db) => ttl_db,
+      None => return Err(Error::NoTtl(self.id)),
+    };
+    encode_k_v!(k, v, {
+      self.purge(k)?;
+      self.check_unique(k, v, false)?;
+      let r = self.put_kv(k, v)?;
+      self.set_ttl(ttl_db, k, Some(deadline(ttl)))?;
+      Ok(r)
+    })
+  }
+
+  // delete the keys whose deadline <= now , at most limit
+  pub fn expire(&mut self, now: u64, limit: usize) -> Result<usize, Error> {
+    let ttl = match self.ttl {
+      Some(ttl) => ttl,
+      None => return Ok(0),
+    };
+    let mut n = 0;
+    while n < limit {
+      let k = {
+        let tx = unsafe { &*self.tx };
+        let db = match tx.root_db::<u64, K, UP<u64, K>>(ttl.deadline_id) {
+          Some(db) => db,
+          None => break,
+        };
+        match btree::iter(tx, &db, None)?.next() {
+          None => break,
+          Some(entry) => {
+            let (deadline, k) = entry?;
+            if *deadline > now {
+              break;
+            }
+            // the deadline page is changed below
+            Owned::new(k)
+          }
+        }
+      };
+      while self.del_kv(&k, None)? {}
+      self.set_ttl(ttl, &k, None)?;
+      n += 1;
+    }
+    Ok(n)
+  }
+
+  // None clears the deadline ; the change is recorded in the tree Ttl::id , so that replicas
+  // follow
+  fn set_ttl(&mut self, ttl: Ttl, k: &K, deadline: Option<u64>) -> Result<(), Error> {
+    let tx = unsafe { &mut *self.tx };
+    let old = match deadline {
+      Some(deadline) => ttl_set(tx, ttl, k, deadline)?,
+      None => ttl_clear(tx, ttl, k)?,
+    };
+    if self.feed().on() && (old.is_some() || deadline.is_some()) {
+      self.feed().push(Change {
+        id: ttl.id,
+        key: bytes(k),
+        old: old.map(|d| bytes(&d)),
+        new: deadline.map(|d| bytes(&d)),
+      });
+    }
+    Ok(())
+  }
+
+  // a Change of another Tx , see Tx::apply_change
+  pub(crate) fn apply(&mut self, change: &Change) -> Result<(), Error> {
+    let k = Owned::<K>::from_bytes(&change.key)?;
+    if let Some(old) = &change.old {
+      self.del_kv(&k, Some(&*Owned::<V>::from_bytes(old)?))?;
+    }
+    if let Some(new) = &change.new {
+      self.put_raw(&k, &Owned::<V>::from_bytes(new)?)?;
+      if change.old.is_some() {
+        self.feed().merge_last();
+      }
+    }
+    Ok(())
+  }
+
+  // an expired key is deleted before it gets new values
+  fn purge(&mut self, k: &K) -> Result<(), Error> {
+    if self.is_expired(k)? {
+      while self.del_kv(k, None)? {}
+    }
+    Ok(())
   }
 
   #[inline]
   pub fn rm1<IntoV: Into<Option<&'b RV>>>(&mut self, k: &RK, v: IntoV) -> Result<bool, Error> {
     match v.into() {
-      Some(v) => {
-        encode_k_v!(
-          k,
-          v,
-          set_root!(btree::del(tx, &mut self.db, k, Some(v)), self, tx)
-        )
-      }
-      None => {
-        encode!(
-          k,
-          set_root!(btree::del(tx, &mut self.db, k, None), self, tx)
-        )
-      }
+      Some(v) => encode_k_v!(k, v, self.del_kv(k, Some(v))),
+      None => encode!(k, self.del_kv(k, None)),
     }
   }
 
   #[inline]
   pub fn upsert(&mut self, k: &RK, v: &RV) -> std::result::Result<bool, Error> {
-    self.rm(k)?;
-    self.put(k, v)
+    encode_k_v!(k, v, {
+      self.purge(k)?;
+      // checked before anything is removed , the write tx commits on drop
+      self.check_unique(k, v, true)?;
+      // del_entry : the deadline of k is kept , as by put
+      while self.del_entry(k, None)? {}
+      let r = self.put_kv(k, v)?;
+      self.feed().merge_last();
+      Ok(r)
+    })
   }
 
   #[inline]
   pub fn rm(&mut self, k: &RK) -> Result<usize, Error> {
-    encode!(
-      k,
-      set_root!(
-        {
-          let db = &mut self.db;
-          let mut n = 0usize;
-          while btree::del(tx, db, k, None)? {
-            n += 1
+    encode!(k, {
+      let mut n = 0usize;
+      while self.del_kv(k, None)? {
+        n += 1
+      }
+      Ok(n)
+    })
+  }
+
+  // the root read by TxDb::db may have been moved or removed by a Savepoint::rollback
+  fn reload(&mut self) -> Result<(), Error> {
+    let gen = unsafe { &*self.owner }.rollbacks.load(Ordering::Relaxed);
+    if gen == self.gen {
+      return Ok(());
+    }
+    let tx = unsafe { &mut *self.tx };
+    self.db = match tx.root_db::<K, V, P>(self.id) {
+      Some(db) => db,
+      None => {
+        let db = create_db_::<_, K, V, P>(tx)?;
+        tx.set_root(self.id, db.db);
+        db
+      }
+    };
+    self.gen = gen;
+    Ok(())
+  }
+
+  // upsert : the values of k are replaced , so k itself does not conflict
+  fn check_unique(&mut self, k: &K, v: &V, upsert: bool) -> Result<(), Error> {
+    self.reload()?;
+    let tx = unsafe { &*self.tx };
+    if !upsert {
+      // put of an existing (k, v) changes nothing
+      if let Some((pk, pv)) = btree::get(tx, &self.db, k, Some(v))? {
+        if pk == k && pv == v {
+          return Ok(());
+        }
+      }
+    }
+    for index in self.index().iter().filter(|i| i.unique) {
+      if let Some(db) = tx.root_db::<[u8], K, UP<[u8], K>>(index.id) {
+        let ik = (index.key)(v);
+        for entry in key_iter(tx, &db, &ik[..])? {
+          let (_, pk) = entry?;
+          if !(upsert && pk == k) {
+            return Err(Error::ConstraintViolation(index.name.clone()));
           }
-          Ok(n)
+        }
+      }
+    }
+    Ok(())
+  }
+
+  #[inline]
+  fn feed(&self) -> &'b Feed {
+    unsafe { &(*self.owner).feed }
+  }
+
+  fn record(&self, k: &K, old: Option<Vec<u8>>, new: Option<Vec<u8>>) {
+    self.feed().push(Change {
+      id: self.id,
+      key: bytes(k),
+      old,
+      new,
+    });
+  }
+
+  fn put_kv(&mut self, k: &K, v: &V) -> Result<bool, Error> {
+    self.reload()?;
+    let r = set_root!(btree::put(tx, &mut self.db, k, v)?, self, tx);
+    if r {
+      let tx = unsafe { &mut *self.tx };
+      for index in self.index() {
+        index_put(tx, index.id, &(index.key)(v), k)?;
+      }
+      if self.feed().on() {
+        self.record(k, None, Some(bytes(v)));
+      }
+    }
+    Ok(r)
+  }
+
+  // v = None : delete the first value of k
+  fn del_kv(&mut self, k: &K, v: Option<&V>) -> Result<bool, Error> {
+    let r = self.del_entry(k, v)?;
+    if let Some(ttl) = self.ttl {
+      // the ttl of k goes with its last value
+      let tx = unsafe { &mut *self.tx };
+      if r && !matches!(btree::get(tx, &self.db, k, None)?, Some((key, _)) if key == k) {
+        self.set_ttl(ttl, k, None)?;
+      }
+    }
+    Ok(r)
+  }
+
+  fn del_entry(&mut self, k: &K, v: Option<&V>) -> Result<bool, Error> {
+    self.reload()?;
+    let index = self.index();
+    let on = self.feed().on();
+    if index.is_empty() && !on {
+      return Ok(set_root!(btree::del(tx, &mut self.db, k, v)?, self, tx));
+    }
+
+    let txr = unsafe { &*self.tx };
+    // index keys and bytes of the deleted value , computed before its page is freed
+    let (ik, old): (Vec<Vec<u8>>, _) = {
+      let v = match v {
+        Some(v) => v,
+        None => match btree::get(txr, &self.db, k, None)? {
+          Some((key, v)) if key == k => v,
+          _ => return Ok(false),
         },
-        self,
-        tx
+      };
+      (
+        index.iter().map(|i| (i.key)(v)).collect(),
+        if on { Some(bytes(v)) } else { None },
       )
-    )
+    };
+
+    if !set_root!(btree::del(tx, &mut self.db, k, v)?, self, tx) {
+      return Ok(false);
+    }
+    if on {
+      self.record(k, old, None);
+    }
+
+    let tx = unsafe { &mut *self.tx };
+    for (index, ik) in index.iter().zip(ik) {
+      // another value of k may still have the same index key
+      let mut other = false;
+      for entry in key_iter(txr, &self.db, k)? {
+        if (index.key)(entry?.1) == ik {
+          other = true;
+          break;
+        }
+      }
+      if !other {
+        index_del(tx, index.id, &ik, k)?;
+      }
+    }
+    Ok(true)
+  }
+
+  // drop the index tree and fill it again from the primary tree
+  pub fn rebuild_index(&mut self, name: &str) -> Result<usize, Error> {
+    self.reload()?;
+    let index = self.find_index(name)?;
+    let tx = unsafe { &mut *self.tx };
+    let mut db = create_db_::<_, [u8], K, UP<[u8], K>>(tx)?;
+    let mut n = 0;
+    // the primary tree is only read here
+    let txr = unsafe { &*self.tx };
+    for entry in btree::iter(txr, &self.db, None)? {
+      let (k, v) = entry?;
+      let ik = (index.key)(v);
+      if index.unique {
+        if let Some((key, pk)) = btree::get(txr, &db, &ik[..], None)? {
+          if key == &ik[..] && pk != k {
+            // the old index tree is kept
+            btree::drop(tx, &db)?;
+            return Err(Error::ConstraintViolation(index.name.clone()));
+          }
+        }
+      }
+      if btree::put(tx, &mut db, &ik[..], k)? {
+        n += 1;
+      }
+    }
+    if let Some(old) = tx.root_db::<[u8], K, UP<[u8], K>>(index.id) {
+      btree::drop(tx, &old)?;
+    }
+    tx.set_root(index.id, db.db);
+    Ok(n)
   }
 }
 
@@ -350,11 +1019,26 @@ pub enum TxArgs<'a> {
   Filename(&'a str),
   InitSize(u64),
   MaxTx(usize),
+  // log the changes of every commit in this tree , see Tx::changes_since
+  Log(usize),
+  // never write : Tx::w returns Error::ReadOnly , the dir and file must exist and are not grown .
+  // The file is not locked , so a reader does not wait for the Tx that writes it
+  ReadOnly,
+  // commits that grow the file past this size fail with Error::Full , see Tx::on_full . The file
+  // is created at this size , InitSize is then ignored ; sanakirja grows it by doubling its map
+  MaxSize(u64),
 }
 
 impl Tx {
   pub fn w(&self) -> Result<WriteTx, Error> {
-    Ok(WriteTx(ManuallyDrop::new(Env::mut_txn_begin(&self.env)?)))
+    if self.read_only {
+      return Err(Error::ReadOnly);
+    }
+    let txn = Env::mut_txn_begin(&self.env)?;
+    Ok(WriteTx {
+      txn: ManuallyDrop::new(txn),
+      tx: self,
+    })
   }
 
   pub fn r(&self) -> Result<ReadTx, Error> {
@@ -362,8 +1046,8 @@ impl Tx {
   }
 
   pub fn db<
-    K: ?Sized + Storable + PartialEq,
-    V: ?Sized + Storable + PartialEq,
+    K: ?Sized + Storable + UnsizedStorable + PartialEq + Verify,
+    V: ?Sized + Storable + UnsizedStorable + PartialEq + Verify,
     P: BTreeMutPage<K, V> + BTreePage<K, V>,
     RK: ?Sized + Encode<K>,
     RV: ?Sized + Encode<V>,
@@ -371,9 +1055,11 @@ impl Tx {
     &self,
     id: usize,
   ) -> DbPage<K, V, P, RK, RV> {
-    if let None = {
+    self.register::<K, V, P>(id, true);
+    // a missing tree of a read only Tx stays missing , see ReadTx::btree
+    if !self.read_only && {
       let tx = Env::txn_begin(&self.env).unwrap();
-      tx.root_db::<K, V, P>(id)
+      tx.root_db::<K, V, P>(id).is_none()
     } {
       let mut w = Env::mut_txn_begin(&self.env).unwrap();
       let tree = create_db_::<_, K, V, P>(&mut w).unwrap();
@@ -384,14 +1070,26 @@ impl Tx {
     DbPage {
       tx: self,
       id,
+      index: Vec::new(),
+      ttl: None,
       _kvp: PhantomData,
     }
   }
 
+  // Tx::open , panics on error
   pub fn new<P: Into<PathBuf>>(dir: P, args: &[TxArgs]) -> Tx {
+    let dir = dir.into();
+    Tx::open(dir.clone(), args).unwrap_or_else(|err| panic!("{} : {}", dir.display(), err))
+  }
+
+  // a missing file is an error under TxArgs::ReadOnly , created otherwise
+  pub fn open<P: Into<PathBuf>>(dir: P, args: &[TxArgs]) -> Result<Tx, Error> {
     let mut filename = None;
     let mut init_size = None;
     let mut max_tx = None;
+    let mut log = None;
+    let mut read_only = false;
+    let mut max_size = 0;
     use TxArgs::*;
 
     for arg in args {
@@ -399,20 +1097,40 @@ impl Tx {
         Filename(i) => filename = i.to_string().into(),
         InitSize(i) => init_size = (*i).into(),
         MaxTx(i) => max_tx = (*i).into(),
+        Log(i) => log = (*i).into(),
+        ReadOnly => read_only = true,
+        MaxSize(i) => max_size = *i,
       }
     }
 
     let dir: PathBuf = dir.into();
     let filename = filename.unwrap_or_else(|| "sdb".into());
-    let init_size = init_size.unwrap_or(1 << 21);
     let max_tx = max_tx.unwrap_or(3);
-
-    create_dir_all(&dir).unwrap();
-
     let file = dir.join(filename);
 
-    Tx {
-      env: Env::new(&file, init_size, max_tx).unwrap(),
-    }
+    // Env::new grows the file to init_size
+    let init_size = if read_only {
+      metadata(&file)?.len()
+    } else {
+      create_dir_all(&dir)?;
+      if max_size > 0 {
+        // the first map covers the cap , see Tx::fits
+        size::cut(&file, max_size, max_tx)?;
+        max_size
+      } else {
+        init_size.unwrap_or(1 << 21)
+      }
+    };
+
+    // Env::new waits for an exclusive lock of the file , held by its writer as long as it is open
+    let env = if read_only {
+      // the lock keeps two writers apart , and this Tx never writes
+      unsafe { Env::new_nolock(&file, init_size, max_tx)? }
+    } else {
+      Env::new(&file, init_size, max_tx)?
+    };
+    Ok(Tx::from_env(
+      env, file, init_size, max_tx, read_only, max_size, log,
+    ))
   }
 }
@@ -0,0 +1,81 @@
+use crate::verify::{verify, Verify};
+use crate::Error;
+use sanakirja::UnsizedStorable;
+use std::any::type_name;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+// an aligned copy of a value read from a page , for keys that must outlive the page
+pub struct Owned<T: ?Sized + UnsizedStorable> {
+  buf: Vec<u64>,
+  _t: PhantomData<T>,
+}
+
+impl<T: ?Sized + UnsizedStorable> Owned<T> {
+  pub fn new(v: &T) -> Self {
+    assert!(T::ALIGN <= 8);
+    let mut buf = vec![0u64; (v.size() + 7) / 8];
+    unsafe { v.write_to_page(buf.as_mut_ptr() as *mut u8) };
+    Owned {
+      buf,
+      _t: PhantomData,
+    }
+  }
+
+  // e.g. the bytes of a Change ; Error::Corruption unless they are exactly one valid T
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error>
+  where
+    T: Verify,
+  {
+    assert!(T::ALIGN <= 8);
+    // a zeroed u64 after the bytes : the size header of a short unsized value is read in bounds
+    let mut buf = vec![0u64; (bytes.len() + 7) / 8 + 1];
+    unsafe {
+      std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf.as_mut_ptr() as *mut u8, bytes.len())
+    };
+    let owned = Owned {
+      buf,
+      _t: PhantomData,
+    };
+    if unsafe { T::onpage_size(owned.buf.as_ptr() as *const u8) } != bytes.len() {
+      return Err(Error::Corruption(type_name::<T>()));
+    }
+    verify(&*owned)?;
+    Ok(owned)
+  }
+
+  // the on-page bytes
+  pub fn bytes(&self) -> &[u8] {
+    let size = unsafe { T::onpage_size(self.buf.as_ptr() as *const u8) };
+    unsafe { std::slice::from_raw_parts(self.buf.as_ptr() as *const u8, size) }
+  }
+}
+
+// the on-page bytes of v
+pub(crate) fn bytes<T: ?Sized + UnsizedStorable>(v: &T) -> Vec<u8> {
+  Owned::new(v).bytes().to_vec()
+}
+
+impl<T: ?Sized + UnsizedStorable> Deref for Owned<T> {
+  type Target = T;
+  #[inline]
+  fn deref(&self) -> &T {
+    unsafe { T::from_raw_ptr(&(), self.buf.as_ptr() as *const u8) }
+  }
+}
+
+impl<T: ?Sized + UnsizedStorable> Clone for Owned<T> {
+  fn clone(&self) -> Self {
+    Owned {
+      buf: self.buf.clone(),
+      _t: PhantomData,
+    }
+  }
+}
+
+impl<T: ?Sized + UnsizedStorable + fmt::Debug> fmt::Debug for Owned<T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    (**self).fmt(f)
+  }
+}
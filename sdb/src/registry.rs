@@ -0,0 +1,305 @@
+use crate::check::{check, Problem};
+use crate::tx::{MutTxnEnv, Tx, TxnEnv};
+use crate::walk::{walk, PageInfo};
+use crate::{bytes, Change, Error, Owned, Verify, WriteTx};
+use sanakirja::btree::{self, create_db_, BTreeMutPage, BTreePage, Db_};
+use sanakirja::{LoadPage, RootDb, UnsizedStorable};
+
+// a Change applied by the DbPage that declared the tree , so that its indexes and ttl follow ,
+// see DbPage::with_index / DbPage::with_ttl
+pub(crate) type WriteFn = dyn for<'a> Fn(&WriteTx<'a>, &Change) -> Result<(), Error> + Send + Sync;
+
+// typed operations on the tree of a DbPage , registered by Tx::db so that code that only knows
+// the tree id (replica ...) can use it
+#[derive(Clone, Copy)]
+pub(crate) struct TreeOps {
+  // the tree of a DbPage , not an index / ttl / log tree
+  pub page: bool,
+  // the key and values of a Change decode to the types of this tree , see Tx::valid_change
+  pub valid: fn(&Change) -> Result<(), Error>,
+  // apply a Change of this tree
+  pub apply: fn(&mut MutTxnEnv, usize, &Change) -> Result<(), Error>,
+  // the on-page bytes of every entry , see Tx::restore_from
+  pub entries: fn(&TxnEnv, usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error>,
+  // same entries in the tree of both txn
+  pub eq: fn(&TxnEnv, &TxnEnv, usize) -> Result<bool, Error>,
+  // replace the tree of dst by the one of src , returns the number of entries
+  pub copy: fn(&TxnEnv, &mut MutTxnEnv, usize) -> Result<u64, Error>,
+  // visit the pages of the tree , see walk
+  pub pages: fn(&TxnEnv, usize, &mut dyn FnMut(&PageInfo) -> bool) -> Result<(), Error>,
+  // entry order and Verify , returns the number of entries
+  pub check: fn(&TxnEnv, usize, &mut Vec<Problem>) -> u64,
+  // a copy on write fork of the root , None if there is no tree , see Savepoint
+  pub fork: fn(&mut MutTxnEnv, usize) -> Result<Option<u64>, Error>,
+  // drop the tree and put back a fork , the root is removed for None
+  pub restore: fn(&mut MutTxnEnv, usize, Option<u64>) -> Result<(), Error>,
+  // drop a fork
+  pub release: fn(&mut MutTxnEnv, u64) -> Result<(), Error>,
+}
+
+impl TreeOps {
+  pub(crate) fn new<K, V, P>(page: bool) -> Self
+  where
+    K: UnsizedStorable + PartialEq + Verify + ?Sized,
+    V: UnsizedStorable + PartialEq + Verify + ?Sized,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+  {
+    TreeOps {
+      page,
+      valid: valid::<K, V>,
+      apply: apply::<K, V, P>,
+      entries: entries::<K, V, P>,
+      eq: eq::<K, V, P>,
+      copy: copy::<K, V, P>,
+      pages: pages::<K, V, P>,
+      check: check::<K, V, P>,
+      fork: fork::<K, V, P>,
+      restore: restore::<K, V, P>,
+      release: release::<K, V, P>,
+    }
+  }
+}
+
+fn valid<K, V>(change: &Change) -> Result<(), Error>
+where
+  K: UnsizedStorable + Verify + ?Sized,
+  V: UnsizedStorable + Verify + ?Sized,
+{
+  Owned::<K>::from_bytes(&change.key)?;
+  for v in change.old.iter().chain(&change.new) {
+    Owned::<V>::from_bytes(v)?;
+  }
+  Ok(())
+}
+
+fn apply<K, V, P>(txn: &mut MutTxnEnv, id: usize, change: &Change) -> Result<(), Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  let mut db = match txn.root_db::<K, V, P>(id) {
+    Some(db) => db,
+    None => create_db_::<_, K, V, P>(txn)?,
+  };
+  let k = Owned::<K>::from_bytes(&change.key)?;
+  if let Some(old) = &change.old {
+    btree::del(txn, &mut db, &*k, Some(&*Owned::<V>::from_bytes(old)?))?;
+  }
+  if let Some(new) = &change.new {
+    btree::put(txn, &mut db, &*k, &*Owned::<V>::from_bytes(new)?)?;
+  }
+  txn.set_root(id, db.db);
+  Ok(())
+}
+
+fn entries<K, V, P>(txn: &TxnEnv, id: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  let mut li = vec![];
+  if let Some(db) = txn.root_db::<K, V, P>(id) {
+    for entry in btree::iter(txn, &db, None)? {
+      let (k, v) = entry?;
+      li.push((bytes(k), bytes(v)));
+    }
+  }
+  Ok(li)
+}
+
+fn eq<K, V, P>(a: &TxnEnv, b: &TxnEnv, id: usize) -> Result<bool, Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  let (da, db) = (a.root_db::<K, V, P>(id), b.root_db::<K, V, P>(id));
+  // a missing tree is the same as an empty one
+  let mut ia = match &da {
+    Some(da) => Some(btree::iter(a, da, None)?),
+    None => None,
+  };
+  let mut ib = match &db {
+    Some(db) => Some(btree::iter(b, db, None)?),
+    None => None,
+  };
+  loop {
+    let ea = next(&mut ia)?;
+    let eb = next(&mut ib)?;
+    match (ea, eb) {
+      (None, None) => return Ok(true),
+      (Some(ea), Some(eb)) if ea == eb => {}
+      _ => return Ok(false),
+    }
+  }
+}
+
+fn copy<K, V, P>(src: &TxnEnv, dst: &mut MutTxnEnv, id: usize) -> Result<u64, Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  if let Some(old) = dst.root_db::<K, V, P>(id) {
+    btree::drop(dst, &old)?;
+  }
+  let mut db = create_db_::<_, K, V, P>(dst)?;
+  let mut n = 0;
+  if let Some(src_db) = src.root_db::<K, V, P>(id) {
+    for entry in btree::iter(src, &src_db, None)? {
+      let (k, v) = entry?;
+      btree::put(dst, &mut db, k, v)?;
+      n += 1;
+    }
+  }
+  dst.set_root(id, db.db);
+  Ok(n)
+}
+
+fn pages<K, V, P>(
+  txn: &TxnEnv,
+  id: usize,
+  f: &mut dyn FnMut(&PageInfo) -> bool,
+) -> Result<(), Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  match txn.root_db::<K, V, P>(id) {
+    Some(db) => walk(txn, &db, f),
+    None => Ok(()),
+  }
+}
+
+fn fork<K, V, P>(txn: &mut MutTxnEnv, id: usize) -> Result<Option<u64>, Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  Ok(match txn.root_db::<K, V, P>(id) {
+    Some(db) => Some(btree::fork_db(txn, &db)?.db),
+    None => None,
+  })
+}
+
+fn restore<K, V, P>(txn: &mut MutTxnEnv, id: usize, fork: Option<u64>) -> Result<(), Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  if let Some(db) = txn.root_db::<K, V, P>(id) {
+    btree::drop(txn, &db)?;
+  }
+  // 0 : no root , as before the tree was made
+  txn.set_root(id, fork.unwrap_or(0));
+  Ok(())
+}
+
+fn release<K, V, P>(txn: &mut MutTxnEnv, fork: u64) -> Result<(), Error>
+where
+  K: UnsizedStorable + PartialEq + Verify + ?Sized,
+  V: UnsizedStorable + PartialEq + Verify + ?Sized,
+  P: BTreeMutPage<K, V> + BTreePage<K, V>,
+{
+  btree::drop(txn, &Db_::<K, V, P>::from_page(fork))?;
+  Ok(())
+}
+
+fn next<'a, T, K, V, P>(
+  iter: &mut Option<btree::Iter<'a, T, K, V, P>>,
+) -> Result<Option<(&'a K, &'a V)>, Error>
+where
+  T: LoadPage<Error = sanakirja::Error>,
+  K: UnsizedStorable + ?Sized + 'a,
+  V: UnsizedStorable + ?Sized + 'a,
+  P: BTreePage<K, V> + 'a,
+{
+  Ok(match iter {
+    Some(iter) => iter.next().transpose()?,
+    None => None,
+  })
+}
+
+impl Tx {
+  pub(crate) fn register<K, V, P>(&self, id: usize, page: bool)
+  where
+    K: UnsizedStorable + PartialEq + Verify + ?Sized,
+    V: UnsizedStorable + PartialEq + Verify + ?Sized,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+  {
+    self
+      .trees
+      .lock()
+      .unwrap()
+      .insert(id, TreeOps::new::<K, V, P>(page));
+  }
+
+  // the trees of the DbPages of other , e.g. the primary of a replica
+  pub(crate) fn learn(&self, other: &Tx) {
+    if std::ptr::eq(self, other) {
+      return;
+    }
+    let theirs = other.trees.lock().unwrap().clone();
+    let mut trees = self.trees.lock().unwrap();
+    for (id, ops) in theirs.into_iter().filter(|(_, ops)| ops.page) {
+      trees.entry(id).or_insert(ops);
+    }
+    let theirs = other.writers.lock().unwrap().clone();
+    let mut writers = self.writers.lock().unwrap();
+    for (id, writer) in theirs {
+      writers.entry(id).or_insert(writer);
+    }
+  }
+
+  // apply a Change of another Tx (Replica , Coordinator) , through the DbPage of the tree when
+  // it has indexes or a ttl ; the change is recorded in the feed of self
+  pub(crate) fn apply_change(&self, w: &WriteTx, change: &Change) -> Result<(), Error> {
+    self.valid_change(change)?;
+    let writer = self.writers.lock().unwrap().get(&change.id).cloned();
+    if let Some(writer) = writer {
+      return writer(w, change);
+    }
+    let ops = self
+      .tree_ops(change.id)
+      .ok_or(Error::UnknownTree(change.id))?;
+    (ops.apply)(
+      unsafe { &mut *(w.ptr() as *mut MutTxnEnv) },
+      change.id,
+      change,
+    )?;
+    if self.feed.on() {
+      self.feed.push(change.clone());
+    }
+    Ok(())
+  }
+
+  // a Change read from a pipe or another file : Error::UnknownTree for a tree not declared on
+  // self , Error::Corruption for bytes that are not its key or value types
+  pub(crate) fn valid_change(&self, change: &Change) -> Result<(), Error> {
+    let ops = self
+      .tree_ops(change.id)
+      .ok_or(Error::UnknownTree(change.id))?;
+    (ops.valid)(change)
+  }
+
+  // the trees whose changes are recorded : DbPages and ttl trees
+  pub(crate) fn recorded(&self, id: usize, ops: &TreeOps) -> bool {
+    ops.page || self.writers.lock().unwrap().contains_key(&id)
+  }
+
+  // registered trees , sorted by id
+  pub(crate) fn tree_list(&self) -> Vec<(usize, TreeOps)> {
+    let mut li: Vec<_> = self.trees.lock().unwrap().clone().into_iter().collect();
+    li.sort_unstable_by_key(|(id, _)| *id);
+    li
+  }
+
+  pub(crate) fn tree_ops(&self, id: usize) -> Option<TreeOps> {
+    self.trees.lock().unwrap().get(&id).copied()
+  }
+}
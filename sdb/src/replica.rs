@@ -0,0 +1,137 @@
+use crate::feed::{last_seq, set_seq};
+use crate::tx::{MutTxnEnv, Tx};
+use crate::{tree, Change, Error, UP};
+use sanakirja::{Env, RootDb};
+use std::io::{self, Read, Write};
+
+// a second Tx that applies the changes logged by a primary (TxArgs::Log) ; a change goes
+// through the DbPage that declared the tree , on the replica or the primary , so that its
+// indexes and ttl follow
+pub struct Replica<'a> {
+  tx: &'a Tx,
+  // the tree of the replica that keeps the applied seq
+  id: usize,
+  seq: u64,
+}
+
+impl<'a> Replica<'a> {
+  pub fn new(tx: &'a Tx, id: usize) -> Result<Self, Error> {
+    let txn = Env::txn_begin(&tx.env)?;
+    let seq = match txn.root_db::<u64, u64, UP<u64, u64>>(id) {
+      Some(db) => last_seq(&txn, &db)?,
+      None => 0,
+    };
+    tx.register::<u64, u64, UP<u64, u64>>(id, false);
+    Ok(Replica { tx, id, seq })
+  }
+
+  // the last applied seq
+  pub fn seq(&self) -> u64 {
+    self.seq
+  }
+
+  // apply the changes of source not applied yet
+  pub fn follow(&mut self, source: &Tx) -> Result<usize, Error> {
+    self.tx.learn(source);
+    let changes = source.changes_since(self.seq)?;
+    self.apply(&changes)
+  }
+
+  // apply changes read from the primary , e.g. with read_changes from a pipe ;
+  // the ones already applied are skipped
+  pub fn apply(&mut self, changes: &[(u64, Change)]) -> Result<usize, Error> {
+    let changes: Vec<&(u64, Change)> = changes.iter().filter(|(seq, _)| *seq > self.seq).collect();
+    if changes.is_empty() {
+      return Ok(0);
+    }
+    // all checked before the first one is applied
+    for (_, change) in &changes {
+      self.tx.valid_change(change)?;
+    }
+
+    let w = self.tx.w()?;
+    let txn = unsafe { &mut *(w.ptr() as *mut MutTxnEnv) };
+    let applied = (|| {
+      let mut seq = self.seq;
+      for (s, change) in changes {
+        if *s != seq + 1 {
+          return Err(Error::LogGap(seq + 1));
+        }
+        self.tx.apply_change(&w, change)?;
+        seq = *s;
+      }
+      let mut db = tree::<u64, u64>(txn, self.id)?;
+      set_seq(txn, &mut db, seq)?;
+      txn.set_root(self.id, db.db);
+      Ok(seq)
+    })();
+    let seq = match applied {
+      Ok(seq) => seq,
+      Err(err) => {
+        w.abort();
+        return Err(err);
+      }
+    };
+    w.commit()?;
+
+    let n = (seq - self.seq) as usize;
+    self.seq = seq;
+    Ok(n)
+  }
+
+  // logged changes of source not applied yet
+  pub fn lag(&self, source: &Tx) -> Result<u64, Error> {
+    Ok(source.log_seq()?.saturating_sub(self.seq))
+  }
+
+  // the ids of the trees declared on source whose entries differ from the replica
+  pub fn check(&self, source: &Tx) -> Result<Vec<usize>, Error> {
+    let a = Env::txn_begin(&source.env)?;
+    let b = Env::txn_begin(&self.tx.env)?;
+    let mut li = vec![];
+    for (id, ops) in source.tree_list() {
+      if ops.page && !(ops.eq)(&a, &b, id)? {
+        li.push(id);
+      }
+    }
+    Ok(li)
+  }
+}
+
+// framing of a batch for a pipe or socket : u32 count , then u64 seq + u32 len + Change bytes
+pub fn write_changes<W: Write>(w: &mut W, changes: &[(u64, Change)]) -> io::Result<()> {
+  w.write_all(&(changes.len() as u32).to_le_bytes())?;
+  for (seq, change) in changes {
+    let b = change.encode();
+    w.write_all(&seq.to_le_bytes())?;
+    w.write_all(&(b.len() as u32).to_le_bytes())?;
+    w.write_all(&b)?;
+  }
+  w.flush()
+}
+
+pub fn read_changes<R: Read>(r: &mut R) -> io::Result<Vec<(u64, Change)>> {
+  let mut u32b = [0u8; 4];
+  let mut u64b = [0u8; 8];
+  r.read_exact(&mut u32b)?;
+  let n = u32::from_le_bytes(u32b);
+  // the counts come from the stream : nothing is allocated before it is read
+  let mut li = Vec::with_capacity((n as usize).min(1024));
+  for _ in 0..n {
+    r.read_exact(&mut u64b)?;
+    r.read_exact(&mut u32b)?;
+    let len = u32::from_le_bytes(u32b) as u64;
+    let mut b = vec![];
+    r.by_ref().take(len).read_to_end(&mut b)?;
+    if b.len() as u64 != len {
+      return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let change =
+      Change::decode(&b).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    li.push((
+      u64::from_le_bytes(<[u8; 8]>::try_from(u64b).unwrap()),
+      change,
+    ));
+  }
+  Ok(li)
+}
@@ -0,0 +1,71 @@
+use crate::registry::TreeOps;
+use crate::tx::MutTxnEnv;
+use crate::{Error, WriteTx};
+use std::sync::atomic::Ordering;
+
+// the roots of every declared tree when WriteTx::savepoint was called ; rollback puts them back
+// and the write tx goes on . A TxDb taken before the rollback reads its root again on next use
+pub struct Savepoint<'w, 'a> {
+  w: &'w WriteTx<'a>,
+  roots: Vec<(usize, TreeOps, Option<u64>)>,
+  // changes of the feed before the savepoint
+  pending: usize,
+  done: bool,
+}
+
+impl<'a> WriteTx<'a> {
+  pub fn savepoint(&self) -> Result<Savepoint<'_, 'a>, Error> {
+    let txn = unsafe { &mut *(self.ptr() as *mut MutTxnEnv) };
+    let mut roots = vec![];
+    for (id, ops) in self.tx.tree_list() {
+      match (ops.fork)(txn, id) {
+        Ok(fork) => roots.push((id, ops, fork)),
+        Err(err) => {
+          release(txn, &roots);
+          return Err(err);
+        }
+      }
+    }
+    Ok(Savepoint {
+      w: self,
+      roots,
+      pending: self.tx.feed.pending_len(),
+      done: false,
+    })
+  }
+}
+
+// a failed release only leaks the pages of the forks
+fn release(txn: &mut MutTxnEnv, roots: &[(usize, TreeOps, Option<u64>)]) {
+  for (_, ops, fork) in roots {
+    if let Some(fork) = fork {
+      let _ = (ops.release)(txn, *fork);
+    }
+  }
+}
+
+impl<'w, 'a> Savepoint<'w, 'a> {
+  // undo the writes made after the savepoint
+  pub fn rollback(mut self) -> Result<(), Error> {
+    self.done = true;
+    let txn = unsafe { &mut *(self.w.ptr() as *mut MutTxnEnv) };
+    for (id, ops, fork) in &self.roots {
+      (ops.restore)(txn, *id, *fork)?;
+    }
+    self.w.tx.rollbacks.fetch_add(1, Ordering::Relaxed);
+    self.w.tx.feed.truncate(self.pending);
+    Ok(())
+  }
+
+  // keep the writes , same as drop
+  pub fn release(self) {}
+}
+
+impl<'w, 'a> Drop for Savepoint<'w, 'a> {
+  fn drop(&mut self) {
+    if !self.done {
+      let txn = unsafe { &mut *(self.w.ptr() as *mut MutTxnEnv) };
+      release(txn, &self.roots);
+    }
+  }
+}
@@ -0,0 +1,143 @@
+use crate::{DbHandle, DbPage, Encode, Error, Tx, TxArgs};
+use sanakirja::btree::{BTreeMutPage, BTreePage};
+use sanakirja::Storable;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+
+// a Tx per shard in dir/<shard> , opened on first use ; at most max_open stay open , the least
+// recently used one that no DbHandle holds is closed first
+pub struct ShardedTx {
+  dir: PathBuf,
+  shards: usize,
+  max_open: usize,
+  args: Vec<TxArgs<'static>>,
+  open: Mutex<Open>,
+  // notified when a shard in Open::opening is open
+  opened: Condvar,
+}
+
+struct Open {
+  // most recently used last
+  txs: Vec<(usize, Arc<Tx>)>,
+  // shards a thread is opening or closing , without the lock
+  opening: Vec<usize>,
+}
+
+impl ShardedTx {
+  pub fn new<P: Into<PathBuf>>(
+    dir: P,
+    shards: usize,
+    max_open: usize,
+    args: Vec<TxArgs<'static>>,
+  ) -> Self {
+    assert!(shards > 0 && max_open > 0);
+    ShardedTx {
+      dir: dir.into(),
+      shards,
+      max_open,
+      args,
+      open: Mutex::new(Open {
+        txs: vec![],
+        opening: vec![],
+      }),
+      opened: Condvar::new(),
+    }
+  }
+
+  // fnv-1a , stable across runs and rust versions unlike std Hash
+  pub fn shard(&self, key: &[u8]) -> usize {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in key {
+      h ^= *b as u64;
+      h = h.wrapping_mul(0x100000001b3);
+    }
+    (h % self.shards as u64) as usize
+  }
+
+  // an error of Tx::open is returned , the next call opens the shard again
+  pub fn tx(&self, shard: usize) -> Result<Arc<Tx>, Error> {
+    assert!(shard < self.shards);
+    let mut open = self.open.lock().unwrap();
+    loop {
+      if let Some(pos) = open.txs.iter().position(|(n, _)| *n == shard) {
+        let entry = open.txs.remove(pos);
+        let tx = entry.1.clone();
+        open.txs.push(entry);
+        return Ok(tx);
+      }
+      if !open.opening.contains(&shard) {
+        break;
+      }
+      // a second Env on the same file would wait for the lock of the first one
+      open = self.opened.wait(open).unwrap();
+    }
+    open.opening.push(shard);
+    drop(open);
+
+    // opening may create and grow the file , the other shards are not blocked meanwhile
+    let dir = self.dir.join(shard.to_string());
+    let tx = Tx::open(&dir, &self.args);
+
+    let mut open = self.open.lock().unwrap();
+    open.opening.retain(|n| *n != shard);
+    self.opened.notify_all();
+    let tx = Arc::new(tx?);
+    let mut closed = None;
+    if open.txs.len() >= self.max_open {
+      // still used shards stay open , over max_open if they all are
+      if let Some(pos) = open
+        .txs
+        .iter()
+        .position(|(_, tx)| Arc::strong_count(tx) == 1)
+      {
+        let entry = open.txs.remove(pos);
+        open.opening.push(entry.0);
+        closed = Some(entry);
+      }
+    }
+    open.txs.push((shard, tx.clone()));
+    drop(open);
+
+    // closed after the lock is released , not reopened before
+    if let Some((n, closed)) = closed {
+      drop(closed);
+      self.open.lock().unwrap().opening.retain(|o| *o != n);
+      self.opened.notify_all();
+    }
+    Ok(tx)
+  }
+
+  // the Tx of the shard of key
+  pub fn get(&self, key: &[u8]) -> Result<Arc<Tx>, Error> {
+    self.tx(self.shard(key))
+  }
+
+  // a DbPage in the shard of key , e.g. sharded.db(tenant, |tx| tx.db(1))
+  pub fn db<K, V, P, RK, RV, F>(&self, key: &[u8], f: F) -> Result<DbHandle<K, V, P, RK, RV>, Error>
+  where
+    K: ?Sized + Storable + PartialEq,
+    V: ?Sized + Storable + PartialEq,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+    F: for<'x> FnOnce(&'x Tx) -> DbPage<'x, K, V, P, RK, RV>,
+  {
+    Ok(DbHandle::new(self.get(key)?, f))
+  }
+
+  // the open shards , least recently used first
+  pub fn open(&self) -> Vec<usize> {
+    self
+      .open
+      .lock()
+      .unwrap()
+      .txs
+      .iter()
+      .map(|(n, _)| *n)
+      .collect()
+  }
+
+  pub fn shards(&self) -> usize {
+    self.shards
+  }
+}
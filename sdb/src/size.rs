@@ -0,0 +1,79 @@
+use crate::free::free_list;
+use crate::tx::Tx;
+use crate::Error;
+use sanakirja::Env;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+// file size over TxArgs::MaxSize -> a new cap , None to fail the commit with Error::Full
+pub(crate) type FullFn = dyn Fn(u64) -> Option<u64> + Send + Sync;
+
+impl Tx {
+  // called when a commit would leave the file over TxArgs::MaxSize , e.g. to free disk space or
+  // alert , then raise the cap
+  pub fn on_full<F: Fn(u64) -> Option<u64> + Send + Sync + 'static>(&self, f: F) {
+    *self.on_full.lock().unwrap() = Some(Arc::new(f));
+  }
+
+  pub fn max_size(&self) -> Option<u64> {
+    match self.max_size.load(Ordering::Relaxed) {
+      0 => None,
+      max => Some(max),
+    }
+  }
+
+  // checked before each commit : the file is mapped at the cap from the start (see Tx::open) ,
+  // so sanakirja grows it only for a write tx whose pages pass the cap , and that commit is
+  // refused . The growth of the map stays until the file is cut at the next Tx::open , and no
+  // commit passes while the file is over the cap
+  pub(crate) fn fits(&self) -> Result<(), Error> {
+    let max = match self.max_size() {
+      None => return Ok(()),
+      Some(max) => max,
+    };
+    let len = fs::metadata(&self.file)?.len();
+    if len <= max {
+      return Ok(());
+    }
+    let on_full = self.on_full.lock().unwrap().clone();
+    if let Some(on_full) = on_full {
+      if let Some(max) = on_full(len) {
+        self.max_size.store(max, Ordering::Relaxed);
+        if len <= max {
+          return Ok(());
+        }
+      }
+    }
+    Err(Error::Full(len))
+  }
+
+  // the error of the last WriteTx that failed to commit on drop , its changes were discarded ;
+  // call WriteTx::commit to get it directly
+  pub fn take_drop_error(&self) -> Option<Error> {
+    self.drop_error.lock().unwrap().take()
+  }
+}
+
+// sanakirja does not unmap the growth of a refused commit while the Env is open : at open , a
+// file over the cap is cut back to the cap , or to its committed pages if they pass it
+pub(crate) fn cut(file: &Path, max: u64, max_tx: usize) -> Result<(), Error> {
+  let len = match fs::metadata(file) {
+    Ok(meta) => meta.len(),
+    Err(_) => return Ok(()),
+  };
+  if len <= max {
+    return Ok(());
+  }
+  let length = {
+    let env = Env::new(file, len, max_tx)?;
+    let txn = Env::txn_begin(&env)?;
+    free_list(&txn, max_tx, len)?.length
+  };
+  OpenOptions::new()
+    .write(true)
+    .open(file)?
+    .set_len(length.max(max))?;
+  Ok(())
+}
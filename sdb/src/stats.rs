@@ -0,0 +1,81 @@
+use crate::free::{free_list, HEADER};
+use crate::tx::{Tx, TxnEnv};
+use crate::walk::PAGE_SIZE;
+use crate::Error;
+use sanakirja::Env;
+use std::fs;
+
+// the root page holds the roots after its header
+pub(crate) const ROOTS: usize = (PAGE_SIZE as usize - HEADER) / 8;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+  pub file: u64,
+  // pages the next write tx can reuse : the free list of sanakirja , and the end of the file it
+  // has not allocated yet ; pages of undeclared trees are not free , see Space::free
+  pub free_pages: u64,
+  // sorted by id
+  pub trees: Vec<TreeStats>,
+}
+
+// a declared tree : DbPage , index , ttl or log
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TreeStats {
+  pub id: usize,
+  pub entries: u64,
+  // pages from the root to a leaf
+  pub depth: usize,
+  pub leaf_pages: u64,
+  pub internal_pages: u64,
+  // size of the keys and values
+  pub bytes: u64,
+  // bytes / page size , averaged over the pages
+  pub fill: f64,
+}
+
+pub(crate) fn roots(txn: &TxnEnv) -> Vec<(usize, u64)> {
+  (0..ROOTS)
+    .filter_map(|id| txn.root(id).map(|offset| (id, offset)))
+    .collect()
+}
+
+impl Tx {
+  // (id , page offset) of every tree in the file , declared or not
+  pub fn roots(&self) -> Result<Vec<(usize, u64)>, Error> {
+    Ok(roots(&Env::txn_begin(&self.env)?))
+  }
+
+  // walk the pages of every declared tree in one read snapshot
+  pub fn stats(&self) -> Result<Stats, Error> {
+    let txn = Env::txn_begin(&self.env)?;
+    let mut trees = vec![];
+    for (id, ops) in self.tree_list() {
+      let mut stats = TreeStats {
+        id,
+        ..Default::default()
+      };
+      (ops.pages)(&txn, id, &mut |page| {
+        stats.entries += page.entries;
+        stats.bytes += page.bytes;
+        stats.depth = stats.depth.max(page.depth + 1);
+        if page.leaf() {
+          stats.leaf_pages += 1
+        } else {
+          stats.internal_pages += 1
+        }
+        true
+      })?;
+      let pages = stats.leaf_pages + stats.internal_pages;
+      if pages > 0 {
+        stats.fill = stats.bytes as f64 / (pages * PAGE_SIZE) as f64;
+      }
+      trees.push(stats);
+    }
+    let file = fs::metadata(&self.file)?.len();
+    Ok(Stats {
+      file,
+      free_pages: free_list(&txn, self.max_tx, file)?.count(file),
+      trees,
+    })
+  }
+}
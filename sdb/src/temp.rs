@@ -0,0 +1,61 @@
+use crate::tx::Tx;
+use crate::TxArgs;
+use sanakirja::Env;
+use std::env::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static N: AtomicUsize = AtomicUsize::new(0);
+
+// removed with the Tx , see Tx::temp
+pub(crate) struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
+
+impl Tx {
+  // a Tx in anonymous memory , gone on drop ; no file , so space / stats / check / backup_to /
+  // compact_into return an Io error
+  pub fn memory(size: u64) -> Tx {
+    let max_tx = 3;
+    Tx::from_env(
+      Env::new_anon(size, max_tx).unwrap(),
+      PathBuf::new(),
+      size,
+      max_tx,
+      false,
+      0,
+      None,
+    )
+  }
+
+  // Tx::new in a fresh dir under the system temp dir , removed on drop
+  pub fn temp(args: &[TxArgs]) -> Tx {
+    let dir = loop {
+      let dir = temp_dir().join(format!(
+        "sdb-{}-{}",
+        process::id(),
+        N.fetch_add(1, Ordering::Relaxed)
+      ));
+      // a dir left by a crashed process with the same pid is skipped
+      match fs::create_dir(&dir) {
+        Ok(()) => break dir,
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+        Err(err) => panic!("{} : {}", dir.display(), err),
+      }
+    };
+    let mut tx = Tx::new(&dir, args);
+    tx.temp = Some(TempDir(dir));
+    tx
+  }
+
+  // empty for Tx::memory
+  pub fn file(&self) -> &Path {
+    &self.file
+  }
+}
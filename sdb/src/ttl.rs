@@ -0,0 +1,152 @@
+use crate::tx::{MutTxnEnv, Tx};
+use crate::{tree, Error, UP};
+use sanakirja::btree::{self, Db_};
+use sanakirja::{LoadPage, UnsizedStorable};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// keys deleted per write tx by Tx::expire_now
+const BATCH: usize = 1024;
+
+// declared by DbPage::with_ttl : k -> deadline in the tree `id` , deadline -> k in `deadline_id`
+#[derive(Clone, Copy, Debug)]
+pub struct Ttl {
+  pub id: usize,
+  pub deadline_id: usize,
+}
+
+// (tx, now, limit) -> number of expired keys , registered by DbPage::with_ttl
+pub(crate) type ExpireFn = dyn Fn(&Tx, u64, usize) -> Result<usize, Error> + Send + Sync;
+
+// milliseconds since the unix epoch
+pub(crate) fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |d| d.as_millis() as u64)
+}
+
+pub(crate) fn deadline(ttl: Duration) -> u64 {
+  now().saturating_add(ttl.as_millis() as u64)
+}
+
+pub(crate) fn ttl_expired<T, K>(
+  tx: &T,
+  db: &Db_<K, u64, UP<K, u64>>,
+  k: &K,
+  now: u64,
+) -> Result<bool, Error>
+where
+  T: LoadPage<Error = sanakirja::Error>,
+  K: UnsizedStorable + PartialEq + ?Sized,
+{
+  Ok(match btree::get(tx, db, k, None)? {
+    Some((key, deadline)) => key == k && *deadline <= now,
+    None => false,
+  })
+}
+
+// returns the previous deadline of k
+pub(crate) fn ttl_set<K: UnsizedStorable + PartialEq + ?Sized>(
+  tx: &mut MutTxnEnv,
+  ttl: Ttl,
+  k: &K,
+  deadline: u64,
+) -> Result<Option<u64>, Error> {
+  let old = ttl_clear(tx, ttl, k)?;
+  let mut db = tree::<K, u64>(tx, ttl.id)?;
+  btree::put(tx, &mut db, k, &deadline)?;
+  tx.set_root(ttl.id, db.db);
+  let mut db = tree::<u64, K>(tx, ttl.deadline_id)?;
+  btree::put(tx, &mut db, &deadline, k)?;
+  tx.set_root(ttl.deadline_id, db.db);
+  Ok(old)
+}
+
+// returns the deadline of k , None if it had none
+pub(crate) fn ttl_clear<K: UnsizedStorable + PartialEq + ?Sized>(
+  tx: &mut MutTxnEnv,
+  ttl: Ttl,
+  k: &K,
+) -> Result<Option<u64>, Error> {
+  let mut db = tree::<K, u64>(tx, ttl.id)?;
+  let deadline = match btree::get(tx, &db, k, None)? {
+    Some((key, deadline)) if key == k => *deadline,
+    _ => return Ok(None),
+  };
+  btree::del(tx, &mut db, k, None)?;
+  tx.set_root(ttl.id, db.db);
+  let mut db = tree::<u64, K>(tx, ttl.deadline_id)?;
+  btree::del(tx, &mut db, &deadline, Some(k))?;
+  tx.set_root(ttl.deadline_id, db.db);
+  Ok(Some(deadline))
+}
+
+impl Tx {
+  // delete the expired entries of every DbPage declared with_ttl , one write tx per batch
+  pub fn expire_now(&self) -> Result<usize, Error> {
+    let li: Vec<Arc<ExpireFn>> = self.expire.lock().unwrap().values().cloned().collect();
+    let now = now();
+    let mut n = 0;
+    for expire in li {
+      loop {
+        let batch = expire(self, now, BATCH)?;
+        n += batch;
+        if batch < BATCH {
+          break;
+        }
+      }
+    }
+    Ok(n)
+  }
+
+  // run expire_now in a background thread until the Expirer is stopped or dropped , or the Tx
+  // is dropped : the thread does not keep it open ; the errors go to on_error
+  pub fn expire_every<F: Fn(Error) + Send + 'static>(
+    self: &Arc<Self>,
+    every: Duration,
+    on_error: F,
+  ) -> Expirer {
+    let tx: Weak<Tx> = Arc::downgrade(self);
+    let (stop, wait) = channel();
+    let thread = thread::spawn(move || loop {
+      match wait.recv_timeout(every) {
+        Err(RecvTimeoutError::Timeout) => {
+          let tx = match tx.upgrade() {
+            Some(tx) => tx,
+            None => return,
+          };
+          if let Err(err) = tx.expire_now() {
+            on_error(err);
+          }
+        }
+        _ => return,
+      }
+    });
+    Expirer {
+      stop,
+      thread: Some(thread),
+    }
+  }
+}
+
+// the thread of Tx::expire_every
+pub struct Expirer {
+  stop: Sender<()>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl Expirer {
+  // wait for a running expire_now to end
+  pub fn stop(self) {}
+}
+
+impl Drop for Expirer {
+  fn drop(&mut self) {
+    let _ = self.stop.send(());
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
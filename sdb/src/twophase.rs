@@ -0,0 +1,304 @@
+use crate::feed::{last_seq, set_seq};
+use crate::tx::{MutTxnEnv, Tx};
+use crate::{bytes, read_changes, tree, write_changes, Change, DbPage, Encode, Error, WriteTx, UP};
+use sanakirja::btree::{self, BTreeMutPage, BTreePage};
+use sanakirja::{Env, RootDb, Storable, UnsizedStorable};
+use std::sync::Mutex;
+
+// commits changes on several Tx all or none : each Tx applies its changes in an open write tx
+// (prepare , a failed put / rm aborts them all) , the changes are written to a journal tree , then
+// every write tx commits with the journal seq , so that recover finishes a commit cut by a crash ;
+// readers can see one Tx committed before the others . Changes go through the DbPages , with
+// their indexes , ttl and unique checks , see Tx::apply_change
+pub struct Coordinator<'a> {
+  journal: &'a Tx,
+  // the journal tree , and the tree of the applied seq in each part
+  id: usize,
+  parts: Vec<&'a Tx>,
+  // commits are applied in seq order
+  lock: Mutex<()>,
+}
+
+// the changes of one commit , see Coordinator::begin
+pub struct Intent<'c, 'a> {
+  coordinator: &'c Coordinator<'a>,
+  // part -> change
+  changes: Vec<(u64, Change)>,
+}
+
+impl<'a> Coordinator<'a> {
+  // the DbPages of the parts must be declared first ; finishes the commits left by a crash
+  pub fn new(journal: &'a Tx, id: usize, parts: Vec<&'a Tx>) -> Result<Self, Error> {
+    assert!(
+      parts.iter().all(|part| !std::ptr::eq(*part, journal)),
+      "the journal Tx cannot be a part"
+    );
+    // a second write tx on the same Tx would wait for the first one
+    for (n, part) in parts.iter().enumerate() {
+      assert!(
+        parts[..n].iter().all(|p| !std::ptr::eq(*p, *part)),
+        "a Tx is twice a part"
+      );
+    }
+    journal.register::<u64, [u8], UP<u64, [u8]>>(id, false);
+    for part in &parts {
+      part.register::<u64, u64, UP<u64, u64>>(id, false);
+    }
+    let coordinator = Coordinator {
+      journal,
+      id,
+      parts,
+      lock: Mutex::new(()),
+    };
+    coordinator.recover()?;
+    Ok(coordinator)
+  }
+
+  pub fn begin(&self) -> Intent<'_, 'a> {
+    Intent {
+      coordinator: self,
+      changes: vec![],
+    }
+  }
+
+  // apply the journaled commits , returns how many
+  pub fn recover(&self) -> Result<usize, Error> {
+    let _lock = self.lock.lock().unwrap();
+    let li = self.pending()?;
+    for (seq, changes) in &li {
+      self.finish(*seq, changes)?;
+    }
+    Ok(li.len())
+  }
+
+  // the journaled commits not applied to every part yet , in seq order
+  fn pending(&self) -> Result<Vec<(u64, Vec<(u64, Change)>)>, Error> {
+    let txn = Env::txn_begin(&self.journal.env)?;
+    let mut li = vec![];
+    if let Some(db) = txn.root_db::<u64, [u8], UP<u64, [u8]>>(self.id) {
+      for entry in btree::iter(&txn, &db, Some((&1, None)))? {
+        let (seq, mut v) = entry?;
+        li.push((*seq, read_changes(&mut v)?));
+      }
+    }
+    Ok(li)
+  }
+
+  fn part(&self, tx: &Tx) -> Result<u64, Error> {
+    self
+      .parts
+      .iter()
+      .position(|part| std::ptr::eq(*part, tx))
+      .map(|n| n as u64)
+      .ok_or(Error::NotAPart)
+  }
+
+  fn commit(&self, changes: &[(u64, Change)]) -> Result<u64, Error> {
+    let _lock = self.lock.lock().unwrap();
+    // applied after it , a pending commit would be skipped by recover : the seq of the parts
+    // only grows
+    if let Some((seq, _)) = self.pending()?.first() {
+      return Err(Error::Pending(*seq));
+    }
+    let mut intent = vec![];
+    write_changes(&mut intent, changes)?;
+    let seq = self.journal_seq()? + 1;
+
+    // prepare : every part applies its changes in a write tx kept open
+    let mut prepared = vec![];
+    for (n, part) in self.parts.iter().enumerate() {
+      let li: Vec<&Change> = changes
+        .iter()
+        .filter(|(p, _)| *p == n as u64)
+        .map(|(_, change)| change)
+        .collect();
+      if li.is_empty() {
+        continue;
+      }
+      // on error the write tx already prepared are aborted by abort_all
+      match self.prepare(part, seq, &li) {
+        Ok(w) => prepared.push(w),
+        Err(err) => {
+          abort_all(prepared);
+          return Err(err);
+        }
+      }
+    }
+
+    let w = match self.journal.w() {
+      Ok(w) => w,
+      Err(err) => {
+        abort_all(prepared);
+        return Err(err);
+      }
+    };
+    let txn = unsafe { &mut *(w.ptr() as *mut MutTxnEnv) };
+    let journaled = (|| -> Result<(), Error> {
+      let mut db = tree::<u64, [u8]>(txn, self.id)?;
+      // key 0 : the last seq , kept when the journal is empty so that seq only grows
+      set_seq(txn, &mut db, seq)?;
+      btree::put(txn, &mut db, &seq, &intent[..])?;
+      txn.set_root(self.id, db.db);
+      Ok(())
+    })();
+    if let Err(err) = journaled.and_then(|_| w.commit()) {
+      abort_all(prepared);
+      return Err(err);
+    }
+
+    // journaled : a part that fails to commit here is applied by recover
+    for w in prepared {
+      w.commit()?;
+    }
+    self.forget(seq)?;
+    Ok(seq)
+  }
+
+  fn journal_seq(&self) -> Result<u64, Error> {
+    let txn = Env::txn_begin(&self.journal.env)?;
+    match txn.root_db::<u64, [u8], UP<u64, [u8]>>(self.id) {
+      Some(db) => last_seq(&txn, &db),
+      None => Ok(0),
+    }
+  }
+
+  // apply a journaled commit to the parts that miss it , then drop it from the journal
+  fn finish(&self, seq: u64, changes: &[(u64, Change)]) -> Result<(), Error> {
+    for (n, part) in self.parts.iter().enumerate() {
+      let li: Vec<&Change> = changes
+        .iter()
+        .filter(|(p, _)| *p == n as u64)
+        .map(|(_, change)| change)
+        .collect();
+      if !li.is_empty() {
+        self.prepare(part, seq, &li)?.commit()?;
+      }
+    }
+    self.forget(seq)
+  }
+
+  fn forget(&self, seq: u64) -> Result<(), Error> {
+    let w = self.journal.w()?;
+    let txn = unsafe { &mut *(w.ptr() as *mut MutTxnEnv) };
+    // the write tx commits on drop : abort on error
+    let forgot = (|| -> Result<(), Error> {
+      let mut db = tree::<u64, [u8]>(txn, self.id)?;
+      btree::del(txn, &mut db, &seq, None)?;
+      txn.set_root(self.id, db.db);
+      Ok(())
+    })();
+    match forgot {
+      Ok(()) => w.commit(),
+      Err(err) => {
+        w.abort();
+        Err(err)
+      }
+    }
+  }
+
+  // the changes and the seq of the part in a write tx left open , nothing if the part has seq
+  // already
+  fn prepare<'p>(&self, part: &'p Tx, seq: u64, changes: &[&Change]) -> Result<WriteTx<'p>, Error> {
+    let w = part.w()?;
+    let txn = unsafe { &mut *(w.ptr() as *mut MutTxnEnv) };
+    let applied = (|| -> Result<(), Error> {
+      let db = tree::<u64, u64>(txn, self.id)?;
+      if last_seq(txn, &db)? >= seq {
+        return Ok(());
+      }
+      for change in changes {
+        part.apply_change(&w, change)?;
+      }
+      // the root may have moved
+      let mut db = tree::<u64, u64>(txn, self.id)?;
+      set_seq(txn, &mut db, seq)?;
+      txn.set_root(self.id, db.db);
+      Ok(())
+    })();
+    match applied {
+      Ok(()) => Ok(w),
+      Err(err) => {
+        w.abort();
+        Err(err)
+      }
+    }
+  }
+}
+
+fn abort_all(li: Vec<WriteTx>) {
+  for w in li {
+    w.abort();
+  }
+}
+
+impl<'c, 'a> Intent<'c, 'a> {
+  // Error::NotAPart if the Tx of db is not a part of the Coordinator
+  pub fn put<K, V, P, RK, RV>(
+    &mut self,
+    db: &DbPage<K, V, P, RK, RV>,
+    k: &RK,
+    v: &RV,
+  ) -> Result<(), Error>
+  where
+    K: ?Sized + Storable + UnsizedStorable + PartialEq,
+    V: ?Sized + Storable + UnsizedStorable + PartialEq,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  {
+    self.push(db, k, None, Some(v))
+  }
+
+  // delete one value of k
+  pub fn rm1<K, V, P, RK, RV>(
+    &mut self,
+    db: &DbPage<K, V, P, RK, RV>,
+    k: &RK,
+    v: &RV,
+  ) -> Result<(), Error>
+  where
+    K: ?Sized + Storable + UnsizedStorable + PartialEq,
+    V: ?Sized + Storable + UnsizedStorable + PartialEq,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  {
+    self.push(db, k, Some(v), None)
+  }
+
+  fn push<K, V, P, RK, RV>(
+    &mut self,
+    db: &DbPage<K, V, P, RK, RV>,
+    k: &RK,
+    old: Option<&RV>,
+    new: Option<&RV>,
+  ) -> Result<(), Error>
+  where
+    K: ?Sized + Storable + UnsizedStorable + PartialEq,
+    V: ?Sized + Storable + UnsizedStorable + PartialEq,
+    P: BTreeMutPage<K, V> + BTreePage<K, V>,
+    RK: ?Sized + Encode<K>,
+    RV: ?Sized + Encode<V>,
+  {
+    let part = self.coordinator.part(db.tx)?;
+    let encode = |v: &RV| v.encode(&mut |v| bytes(v));
+    self.changes.push((
+      part,
+      Change {
+        id: db.id,
+        key: k.encode(&mut |k| bytes(k)),
+        old: old.map(encode),
+        new: new.map(encode),
+      },
+    ));
+    Ok(())
+  }
+
+  // journal , then apply to every part ; returns the journal seq , 0 if there was no change
+  pub fn commit(self) -> Result<u64, Error> {
+    if self.changes.is_empty() {
+      return Ok(0);
+    }
+    self.coordinator.commit(&self.changes)
+  }
+}
@@ -1,8 +1,73 @@
+use crate::feed::Feed;
+use crate::registry::{TreeOps, WriteFn};
+use crate::size::FullFn;
+use crate::temp::TempDir;
+use crate::ttl::ExpireFn;
+use crate::{Error, UP};
 use sanakirja::{Env, MutTxn, Txn};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
 
 pub type MutTxnEnv<'a> = MutTxn<&'a Env, ()>;
 pub type TxnEnv<'a> = Txn<&'a Env>;
 
 pub struct Tx {
   pub(crate) env: Env,
+  pub(crate) file: PathBuf,
+  pub(crate) init_size: u64,
+  pub(crate) max_tx: usize,
+  // TxArgs::ReadOnly
+  pub(crate) read_only: bool,
+  // TxArgs::MaxSize , 0 for none
+  pub(crate) max_size: AtomicU64,
+  pub(crate) on_full: Mutex<Option<Arc<FullFn>>>,
+  // see Tx::take_drop_error
+  pub(crate) drop_error: Mutex<Option<Error>>,
+  // DbPage id -> expiry of its ttl entries , see DbPage::with_ttl
+  pub(crate) expire: Mutex<HashMap<usize, Arc<ExpireFn>>>,
+  pub(crate) feed: Feed,
+  // DbPage id -> typed operations on its tree
+  pub(crate) trees: Mutex<HashMap<usize, TreeOps>>,
+  // tree id -> put / rm of a Change through its DbPage , see Tx::apply_change
+  pub(crate) writers: Mutex<HashMap<usize, Arc<WriteFn>>>,
+  // Savepoint::rollback count , a TxDb reads its root again when it changes
+  pub(crate) rollbacks: AtomicU64,
+  // Tx::temp , last so that env is dropped first
+  pub(crate) temp: Option<TempDir>,
+}
+
+impl Tx {
+  // every Tx is made here , see Tx::new / Tx::memory
+  pub(crate) fn from_env(
+    env: Env,
+    file: PathBuf,
+    init_size: u64,
+    max_tx: usize,
+    read_only: bool,
+    max_size: u64,
+    log: Option<usize>,
+  ) -> Tx {
+    let tx = Tx {
+      env,
+      file,
+      init_size,
+      max_tx,
+      read_only,
+      max_size: AtomicU64::new(max_size),
+      on_full: Mutex::new(None),
+      drop_error: Mutex::new(None),
+      expire: Mutex::new(HashMap::new()),
+      feed: Feed::new(log),
+      trees: Mutex::new(HashMap::new()),
+      writers: Mutex::new(HashMap::new()),
+      rollbacks: AtomicU64::new(0),
+      temp: None,
+    };
+    if let Some(id) = log {
+      tx.register::<u64, [u8], UP<u64, [u8]>>(id, false);
+    }
+    tx
+  }
 }
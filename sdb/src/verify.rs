@@ -0,0 +1,95 @@
+use crate::Error;
+use std::any::type_name;
+use std::num::{
+  NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+  NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+};
+use std::ptr;
+
+// check the bit pattern of a value read from a page before it is handed out
+pub trait Verify {
+  #[inline]
+  fn verify(&self) -> bool {
+    true
+  }
+}
+
+#[inline]
+pub(crate) fn verify<T: ?Sized + Verify>(v: &T) -> Result<(), Error> {
+  if v.verify() {
+    Ok(())
+  } else {
+    Err(Error::Corruption(type_name::<T>()))
+  }
+}
+
+macro_rules! verify_li {
+  ( $( $x:ty ),* ) => {
+    $(impl Verify for $x {})*
+  };
+}
+
+verify_li!(
+  [u8],
+  i8,
+  u8,
+  i16,
+  u16,
+  i32,
+  u32,
+  i64,
+  u64,
+  i128,
+  u128,
+  isize,
+  usize,
+  f32,
+  f64
+);
+
+impl Verify for bool {
+  #[inline]
+  fn verify(&self) -> bool {
+    unsafe { ptr::read(self as *const bool as *const u8) <= 1 }
+  }
+}
+
+impl Verify for char {
+  #[inline]
+  fn verify(&self) -> bool {
+    char::from_u32(unsafe { ptr::read(self as *const char as *const u32) }).is_some()
+  }
+}
+
+macro_rules! verify_non_zero {
+  ( $( $x:ty : $i:ty ),* ) => {
+    $(impl Verify for $x {
+      #[inline]
+      fn verify(&self) -> bool {
+        unsafe { ptr::read(self as *const $x as *const $i) != 0 }
+      }
+    })*
+  };
+}
+
+verify_non_zero!(
+  NonZeroI8: i8,
+  NonZeroU8: u8,
+  NonZeroI16: i16,
+  NonZeroU16: u16,
+  NonZeroI32: i32,
+  NonZeroU32: u32,
+  NonZeroI64: i64,
+  NonZeroU64: u64,
+  NonZeroI128: i128,
+  NonZeroU128: u128,
+  NonZeroIsize: isize,
+  NonZeroUsize: usize
+);
+
+impl<T: Verify, const N: usize> Verify for [T; N] {
+  #[inline]
+  fn verify(&self) -> bool {
+    self.iter().all(Verify::verify)
+  }
+}
@@ -0,0 +1,79 @@
+use crate::Error;
+use std::any::type_name;
+use std::borrow::Cow;
+use std::mem::{align_of, size_of};
+use std::{ptr, slice};
+
+/// # Safety
+/// plain old data that can be read straight from page bytes : `#[repr(C)]` , no padding , no
+/// pointers . `valid` must return false for any bytes that are not a valid Self ; the default
+/// only fits types valid for every bit pattern , a type with bool / char / enum fields checks them
+pub unsafe trait View: Copy {
+  // size_of::<Self>() bytes , checked before a Self is made from them
+  #[inline]
+  fn valid(_bytes: &[u8]) -> bool {
+    true
+  }
+}
+
+macro_rules! view_li {
+  ( $( $x:ty ),* ) => {
+    $(unsafe impl View for $x {})*
+  };
+}
+
+view_li!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize, f32, f64);
+
+unsafe impl View for bool {
+  #[inline]
+  fn valid(bytes: &[u8]) -> bool {
+    bytes[0] <= 1
+  }
+}
+
+unsafe impl View for char {
+  #[inline]
+  fn valid(bytes: &[u8]) -> bool {
+    let mut n = [0; 4];
+    n.copy_from_slice(bytes);
+    char::from_u32(u32::from_ne_bytes(n)).is_some()
+  }
+}
+
+unsafe impl<T: View, const N: usize> View for [T; N] {
+  #[inline]
+  fn valid(bytes: &[u8]) -> bool {
+    size_of::<T>() == 0 || bytes.chunks_exact(size_of::<T>()).all(T::valid)
+  }
+}
+
+// borrow the bytes as T when they are aligned , else copy T out of them
+pub fn view<T: View>(bytes: &[u8]) -> Result<Cow<T>, Error> {
+  if bytes.len() != size_of::<T>() || !T::valid(bytes) {
+    return Err(Error::Corruption(type_name::<T>()));
+  }
+  let p = bytes.as_ptr();
+  Ok(if p as usize % align_of::<T>() == 0 {
+    Cow::Borrowed(unsafe { &*(p as *const T) })
+  } else {
+    Cow::Owned(unsafe { ptr::read_unaligned(p as *const T) })
+  })
+}
+
+pub fn view_slice<T: View>(bytes: &[u8]) -> Result<Cow<[T]>, Error> {
+  let size = size_of::<T>();
+  if size == 0 || bytes.len() % size != 0 || !bytes.chunks_exact(size).all(T::valid) {
+    return Err(Error::Corruption(type_name::<T>()));
+  }
+  let len = bytes.len() / size;
+  let p = bytes.as_ptr();
+  Ok(if p as usize % align_of::<T>() == 0 {
+    Cow::Borrowed(unsafe { slice::from_raw_parts(p as *const T, len) })
+  } else {
+    Cow::Owned(
+      (0..len)
+        .map(|i| unsafe { ptr::read_unaligned((p as *const T).add(i)) })
+        .collect(),
+    )
+  })
+}
@@ -0,0 +1,71 @@
+use crate::Error;
+use sanakirja::btree::{BTreePage, Db_};
+use sanakirja::{LoadPage, UnsizedStorable};
+
+pub(crate) const PAGE_SIZE: u64 = 4096;
+
+// a page of a tree , see walk
+pub(crate) struct PageInfo {
+  pub offset: u64,
+  // 0 : the root page
+  pub depth: usize,
+  pub entries: u64,
+  // size of the keys and values
+  pub bytes: u64,
+  pub children: Vec<u64>,
+}
+
+impl PageInfo {
+  pub(crate) fn leaf(&self) -> bool {
+    self.children.is_empty()
+  }
+}
+
+// visit every page of the tree , parents before their children ; f returns false to skip the
+// children of a page (e.g. a corrupted pointer)
+pub(crate) fn walk<T, K, V, P>(
+  txn: &T,
+  db: &Db_<K, V, P>,
+  f: &mut dyn FnMut(&PageInfo) -> bool,
+) -> Result<(), Error>
+where
+  T: LoadPage<Error = sanakirja::Error>,
+  K: UnsizedStorable + ?Sized,
+  V: UnsizedStorable + ?Sized,
+  P: BTreePage<K, V>,
+{
+  let mut stack = vec![(db.db, 0)];
+  while let Some((offset, depth)) = stack.pop() {
+    let page = txn.load_page(offset)?;
+    let mut cursor = P::cursor_before(&page);
+    let mut children = vec![];
+    let left = P::left_child(page.as_page(), &cursor);
+    if left > 0 {
+      children.push(left);
+    }
+    let mut entries = 0;
+    let mut bytes = 0;
+    while let Some((k, v, right)) = P::next(txn, page.as_page(), &mut cursor) {
+      entries += 1;
+      bytes += (k.size() + v.size()) as u64;
+      if right > 0 {
+        children.push(right);
+      }
+    }
+    let info = PageInfo {
+      offset,
+      depth,
+      entries,
+      bytes,
+      children,
+    };
+    if !f(&info) {
+      continue;
+    }
+    // the low bits of a child pointer are flags
+    for child in info.children.iter().rev() {
+      stack.push((child & !(PAGE_SIZE - 1), depth + 1));
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use sdb::{Db, Page, Tx, TxArgs};
+
+#[test]
+fn backup() -> Result<()> {
+  let tx = Tx::temp(&[TxArgs::Log(0)]);
+  let db: Db<u64, u64> = tx.db(1).with_index("v", 2, |v: &u64| *v);
+  db.put(&1, &10)?;
+  db.put(&2, &20)?;
+  // a tree no DbPage declares
+  tx.w()?.btree::<u64, u64, Page<u64, u64>>(9);
+
+  let file = tx.file().with_file_name("sdb.bak");
+  let (n, skipped) = tx.backup_to(&file)?;
+  assert!(n >= 4);
+  assert_eq!(skipped, [9]);
+  assert!(!file.with_file_name("sdb.bak.partial").exists());
+  assert!(tx.backup_to(&file).is_err());
+  // a failed backup leaves no file behind
+  let missing = file.with_file_name("no_dir").join("sdb.bak");
+  assert!(tx.backup_to(&missing).is_err());
+  assert!(!missing.with_file_name("sdb.bak.partial").exists());
+
+  db.rm(&1)?;
+  db.upsert(&2, &30)?;
+  let seq = tx.log_seq()?;
+  let changes = tx.subscribe();
+  tx.restore_from(&file)?;
+  assert_eq!(db.one(&1)?, Some(&10));
+  assert_eq!(db.one(&2)?, Some(&20));
+  assert_eq!(db.by_index("v", &20u64)?.len(), 1);
+  assert!(db.by_index("v", &30u64)?.is_empty());
+
+  // the restore is logged after the changes before it
+  assert!(tx.log_seq()? > seq);
+  let li: Vec<_> = changes.try_iter().collect();
+  assert_eq!(li.iter().filter(|c| c.old.is_some()).count(), 1);
+  assert_eq!(li.iter().filter(|c| c.new.is_some()).count(), 2);
+  assert_eq!(
+    tx.changes_since(seq)?
+      .into_iter()
+      .map(|(_, c)| c)
+      .collect::<Vec<_>>(),
+    li
+  );
+  Ok(())
+}
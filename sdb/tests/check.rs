@@ -0,0 +1,75 @@
+use anyhow::Result;
+use sdb::{Db, MutTxnEnv, Problem, Tx, TxArgs};
+use std::fs::{self, OpenOptions};
+
+#[test]
+fn root_out_of_file() -> Result<()> {
+  let tx = Tx::temp(&[]);
+  let db: Db<u64, u64> = tx.db(1);
+  db.put(&1, &1)?;
+  let report = tx.check()?;
+  assert!(report.ok(), "{:?}", report.problems);
+
+  let offset = tx.space()?.file + (1 << 20);
+  {
+    let w = tx.w()?;
+    unsafe { &mut *(w.ptr() as *mut MutTxnEnv) }.set_root(2, offset);
+    w.commit()?;
+  }
+  let _: Db<u64, u64> = tx.db(2);
+  let report = tx.check()?;
+  assert_eq!(report.problems, vec![Problem::OutOfFile { id: 2, offset }]);
+  // the other trees are still checked
+  assert_eq!(report.entries, 1);
+  Ok(())
+}
+
+#[test]
+fn child_out_of_file() -> Result<()> {
+  let tx = Tx::temp(&[]);
+  let db: Db<u64, u64> = tx.db(1);
+  {
+    let w = tx.w()?;
+    let mut t = w.db(&db);
+    for i in 0..5000u64 {
+      t.put(&i, &i)?;
+    }
+  }
+  let root = tx.roots()?[0].1 & !4095;
+  let mut dot = vec![];
+  tx.r()?.db(&db).to_dot(&mut dot)?;
+  // the offset of every page , "  p<offset> [label=..."
+  let last = String::from_utf8(dot)?
+    .lines()
+    .filter_map(|line| {
+      line
+        .trim()
+        .strip_prefix('p')?
+        .split_once(" [")?
+        .0
+        .parse()
+        .ok()
+    })
+    .max()
+    .unwrap_or(0u64);
+  // leaves split after the root was made are above it
+  assert!(last > root);
+
+  // a copy cut before the last page , the file of tx stays open
+  let dir = tx.file().parent().unwrap();
+  fs::copy(tx.file(), dir.join("cut"))?;
+  OpenOptions::new()
+    .write(true)
+    .open(dir.join("cut"))?
+    .set_len(last)?;
+  let cut = Tx::new(dir, &[TxArgs::Filename("cut"), TxArgs::ReadOnly]);
+  let _: Db<u64, u64> = cut.db(1);
+  let report = cut.check()?;
+  assert!(report
+    .problems
+    .iter()
+    .any(|p| matches!(p, Problem::OutOfFile { id: 1, .. })));
+  // the entries are not read
+  assert_eq!(report.entries, 0);
+  Ok(())
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use sdb::{Db, Error, Tx, TxArgs};
+use std::fs;
+
+#[test]
+fn compact() -> Result<()> {
+  let mut tx = Tx::temp(&[TxArgs::InitSize(1 << 16)]);
+  let new_file = tx.file().with_file_name("sdb.compact");
+  {
+    let db: Db<u64, u64> = tx.db(1);
+    {
+      let w = tx.w()?;
+      let mut db = w.db(&db);
+      for i in 0..20000u64 {
+        db.put(&i, &i)?;
+      }
+    }
+    let w = tx.w()?;
+    let mut db = w.db(&db);
+    for i in 0..19990u64 {
+      db.rm(&i)?;
+    }
+  }
+  let space = tx.space()?;
+  assert!(space.free > space.live);
+  // the pages of the deleted entries are in the free list
+  let stats = tx.stats()?;
+  assert!(stats.free_pages * 4096 > space.live);
+  assert!(stats.free_pages * 4096 <= space.file);
+
+  let reclaimed = tx.compact_into(&new_file)?;
+  assert!(reclaimed > 0);
+  assert!(!new_file.exists());
+  let after = tx.space()?;
+  assert_eq!(after.file, space.file - reclaimed);
+  assert!(after.file < space.file);
+  assert!(after.free < space.free);
+  let db: Db<u64, u64> = tx.db(1);
+  assert_eq!(db.one(&19995)?, Some(&19995));
+  assert_eq!(db.iter(None, None)?.count(), 10);
+  Ok(())
+}
+
+#[test]
+fn compact_undeclared() -> Result<()> {
+  let tx = Tx::temp(&[]);
+  let a: Db<u64, u64> = tx.db(1);
+  let b: Db<u64, u64> = tx.db(2);
+  a.put(&1, &1)?;
+  b.put(&2, &2)?;
+  // a copy , the file of tx stays open
+  let dir = tx.file().parent().unwrap();
+  fs::copy(tx.file(), dir.join("copy"))?;
+  let len = fs::metadata(dir.join("copy"))?.len();
+
+  let mut copy = Tx::new(dir, &[TxArgs::Filename("copy")]);
+  let _: Db<u64, u64> = copy.db(1);
+  assert!(matches!(
+    copy.compact_into(dir.join("new")),
+    Err(Error::UnknownTree(2))
+  ));
+  assert!(!dir.join("new").exists());
+  assert_eq!(fs::metadata(dir.join("copy"))?.len(), len);
+  let _: Db<u64, u64> = copy.db(2);
+  copy.compact_into(dir.join("new"))?;
+  let b: Db<u64, u64> = copy.db(2);
+  assert_eq!(b.one(&2)?, Some(&2));
+  Ok(())
+}
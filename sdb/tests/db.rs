@@ -1,35 +1,19 @@
 use desse::{Desse, DesseSized};
-use sdb::{desse, Db, DbEv, DbU, Encode, Sdb, Storable, Tx, UnsizedStorable};
+use sdb::{desse, Db, DbDesse, DbU, Sdb, Storable, Tx, UnsizedStorable};
 use static_init::dynamic;
-use std::env;
-use std::path::Path;
 
-#[dynamic]
-pub static DIR: String = env::current_exe()
-  .unwrap()
-  .parent()
-  .unwrap()
-  .parent()
-  .unwrap()
-  .display()
-  .to_string();
-
-#[dynamic]
+// removed at exit
+#[dynamic(lazy, drop)]
 pub static TX: Tx = {
-  let dir = Path::new(&*DIR).join("db");
-
-  println!("DATABASE DIR {}", dir.display().to_string());
-
   //use sdb::TxArgs::{InitSize, MaxTx, Filename};
 
-  Tx::new(
-    &dir,
-    &[
-      //MaxTx(3),
-      //Filename("sdb"),
-      //InitSize(1<<21),
-    ],
-  )
+  let tx = Tx::temp(&[
+    //MaxTx(3),
+    //Filename("sdb"),
+    //InitSize(1<<21),
+  ]);
+  println!("DATABASE FILE {}", tx.file().display());
+  tx
 };
 
 #[dynamic]
@@ -56,6 +40,55 @@ pub struct Data {
 #[dynamic]
 pub static DB4: Db<'static, u64, Data> = TX.db(4);
 
+#[derive(Sdb, Default, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[sdb(order_by = "id desc, hash")] // tree order , Ord is not used
+pub struct Data3 {
+  pub hash: [u8; 3],
+  pub id: u64,
+}
+
+#[dynamic]
+pub static DB6: Db<'static, Data3, u64> = TX.db(6);
+
+// the discriminant is checked on read , an unknown one is Error::Corruption
+#[derive(Sdb, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[repr(u8)]
+pub enum State {
+  Init,
+  Run(u32) = 3,
+  Stop,
+}
+
+#[dynamic]
+pub static DB7: Db<'static, u64, State> = TX.db(7);
+
+// the bytes of a State , to write bad discriminants in the tree 7
+#[derive(Sdb, Default, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[repr(C, align(4))]
+pub struct Raw8(pub [u8; 8]);
+
+#[dynamic]
+pub static DB7_RAW: Db<'static, u64, Raw8> = TX.db(7);
+
+// check bool / enum fields on read
+#[derive(Sdb, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[sdb(validate)]
+pub struct Flag {
+  pub on: bool,
+  pub state: State,
+}
+
+#[dynamic]
+pub static DB8: Db<'static, u64, Flag> = TX.db(8);
+
+// the bytes of a Flag , to write bad bool / enum fields in the tree 8
+#[derive(Sdb, Default, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[repr(C, align(4))]
+pub struct Raw12(pub [u8; 12]);
+
+#[dynamic]
+pub static DB8_RAW: Db<'static, u64, Raw12> = TX.db(8);
+
 #[derive(DesseSized, Desse, Debug)]
 pub struct Data2 {
   pub hash: [u8; 3],
@@ -66,13 +99,17 @@ desse!(Data2, Data2Desse); // the same as below
 
 /*
 #[derive(
-  Default, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, Debug, DesseSized, Desse,
+  Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, Debug, DesseSized, Desse,
 )]
 pub struct Data2Desse([u8; Data2::SIZE]);
 
 use sdb::direct_repr;
 direct_repr!(Data2Desse);
 
+impl DesseRepr for Data2 {
+  type Repr = Data2Desse;
+}
+
 #[dynamic]
 pub static DB5: DbEv<'static, u64, Data2Desse, Data2> = TX.db(5);
 
@@ -90,5 +127,19 @@ impl From<&Data2Desse> for Data2 {
 }
 */
 
+#[dynamic] // DbDesse<K, V> = DbEv<K, Data2Desse, V>
+pub static DB5: DbDesse<'static, u64, Data2> = TX.db(5);
+
+// Data.id -> key , in the tree 10
 #[dynamic]
-pub static DB5: DbEv<'static, u64, Data2Desse, Data2> = TX.db(5);
+pub static DB9: Db<'static, u64, Data> = TX.db(9).with_index("id", 10, |v: &Data| v.id);
+
+#[dynamic]
+pub static DB10: Db<'static, u64, Data> = TX.db(11).with_unique("hash", 12, |v: &Data| v.hash);
+
+// k -> deadline in the tree 14 , deadline -> k in 15
+#[dynamic]
+pub static DB11: Db<'static, u64, u64> = TX.db(13).with_ttl(14, 15);
+
+#[dynamic]
+pub static DB12: Db<'static, u64, u64> = TX.db(16);
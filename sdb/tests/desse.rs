@@ -0,0 +1,75 @@
+use anyhow::Result;
+use desse::{Desse, DesseSized};
+use sdb::{desse, DbDesse, DbDesseK, DbDesseKv, Tx};
+use static_init::dynamic;
+
+#[dynamic]
+static TX: Tx = Tx::memory(1 << 21);
+
+#[derive(DesseSized, Desse, PartialEq, Clone, Copy, Debug)]
+pub struct User {
+  pub id: u64,
+  pub age: u8,
+}
+
+#[derive(DesseSized, Desse, PartialEq, Clone, Copy, Debug)]
+pub struct Point(i32, i32);
+
+#[derive(DesseSized, Desse, PartialEq, Clone, Copy, Debug)]
+pub struct Sha {
+  pub hash: [u8; 40],
+  pub len: u32,
+}
+
+// round-trip a desse type as value , key , and both
+macro_rules! desse_test {
+  ($test:ident, $cls:ident, $desse:ident, $id:expr, $v:expr) => {
+    desse!($cls, $desse);
+
+    #[test]
+    fn $test() -> Result<()> {
+      let v: $cls = $v;
+
+      let db: DbDesse<u64, $cls> = TX.db($id);
+      db.upsert(&1, &v)?;
+      db.put(&2, &v)?;
+      assert_eq!(db.get(&1)?, Some(v));
+      assert_eq!(db.get_all(&2)?, vec![v]);
+      assert_eq!(db.get(&3)?, None);
+
+      let db_k: DbDesseK<$cls, u64> = TX.db($id + 1);
+      db_k.upsert(&v, &7)?;
+
+      let db_kv: DbDesseKv<$cls, $cls> = TX.db($id + 2);
+      db_kv.upsert(&v, &v)?;
+      assert_eq!(db_kv.get(&v)?, Some(v));
+
+      {
+        let tx = TX.r()?;
+        let db_k = tx.db(&db_k);
+        assert_eq!(db_k.one(&v)?, Some(&7));
+        for entry in db_k.iter(None, None)? {
+          let (k, _) = entry?;
+          assert_eq!($cls::from(k), v);
+        }
+      }
+
+      db_kv.rm(&v)?;
+      assert_eq!(db_kv.get(&v)?, None);
+      Ok(())
+    }
+  };
+}
+
+desse_test!(user, User, UserDesse, 0, User { id: 9, age: 3 });
+desse_test!(point, Point, PointDesse, 3, Point(-1, 2));
+desse_test!(
+  sha,
+  Sha,
+  ShaDesse,
+  6,
+  Sha {
+    hash: [7; 40],
+    len: 40
+  }
+);
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use sdb::{Db, DbU, Error, Format, Tx};
+
+#[test]
+fn export() -> Result<()> {
+  let tx = Tx::memory(1 << 21);
+  let num: Db<u64, i64> = tx.db(1);
+  let bin: DbU<u64, [u8]> = tx.db(2);
+  {
+    let w = tx.w()?;
+    let mut num = w.db(&num);
+    let mut bin = w.db(&bin);
+    for i in 0..3000u64 {
+      num.put(&i, &-(i as i64))?;
+    }
+    bin.put(&1, &[0, 255][..])?;
+    bin.put(&2, b"a,\"b")?;
+  }
+
+  let mut jsonl = vec![];
+  assert_eq!(num.export(&mut jsonl, Format::Jsonl)?, 3000);
+  assert!(jsonl.starts_with(b"{\"key\":0,\"value\":0}\n{\"key\":1,\"value\":-1}\n"));
+  let mut csv = vec![];
+  assert_eq!(bin.export(&mut csv, Format::Csv)?, 2);
+  assert_eq!(csv, b"key,value\n1,00ff\n2,612c2262\n");
+
+  let num2: Db<u64, i64> = tx.db(3);
+  assert_eq!(num2.import(&jsonl[..], Format::Jsonl)?, 3000);
+  assert_eq!(num2.one(&2999)?, Some(&-2999));
+  let bin2: DbU<u64, [u8]> = tx.db(4);
+  assert_eq!(bin2.import(&csv[..], Format::Csv)?, 2);
+  assert_eq!(bin2.one(&2)?, Some(&b"a,\"b"[..]));
+  assert_eq!(
+    bin2.import(&b"{ \"value\" : \"ff\", \"key\" : 3 }\n"[..], Format::Jsonl)?,
+    1
+  );
+  assert_eq!(bin2.one(&3)?, Some(&[255u8][..]));
+
+  match bin2.import(&b"key,value\n4,00\n5,0\n"[..], Format::Csv) {
+    Err(Error::Parse(3, _)) => {}
+    r => panic!("{:?}", r),
+  }
+  // the failing batch is not committed
+  assert_eq!(bin2.one(&4)?, None);
+  Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use sdb::{Handle, HandleU, Tx};
+use std::sync::Arc;
+use std::thread;
+
+fn tenant() -> (Handle<u64, u64>, HandleU<u64, [u8]>) {
+  let tx = Arc::new(Tx::temp(&[]));
+  (
+    Handle::new(tx.clone(), |tx| tx.db(1)),
+    HandleU::new(tx, |tx| tx.db(2)),
+  )
+}
+
+#[test]
+fn handle() -> Result<()> {
+  let (a, a_bin) = tenant();
+  let (b, _) = tenant();
+  let dir = a.tx().file().parent().unwrap().to_path_buf();
+
+  let li: Vec<_> = (0..4u64)
+    .map(|i| {
+      let a = a.clone();
+      thread::spawn(move || a.page().put(&i, &i).unwrap())
+    })
+    .collect();
+  for t in li {
+    t.join().unwrap();
+  }
+  a_bin.page().put(&1, b"x")?;
+  b.page().put(&1, &9)?;
+
+  assert_eq!(a.page().iter(None, None)?.count(), 4);
+  assert_eq!(b.page().one(&1)?, Some(&9));
+  {
+    let w = a.tx().w()?;
+    w.db(a.page()).put(&5, &5)?;
+    w.db(a_bin.page()).put(&2, b"y")?;
+  }
+  assert_eq!(a.page().one(&5)?, Some(&5));
+  assert_eq!(a_bin.page().one(&2)?, Some(&b"y"[..]));
+
+  drop(a);
+  drop(a_bin);
+  assert!(!dir.exists());
+  Ok(())
+}
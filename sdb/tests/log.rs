@@ -0,0 +1,35 @@
+use anyhow::Result;
+use sdb::{Db, Owned, Tx, TxArgs};
+
+#[test]
+fn log() -> Result<()> {
+  let tx = Tx::temp(&[TxArgs::Log(0)]);
+  let db: Db<u64, u64> = tx.db(1);
+  db.rm(&1)?;
+  let seq = tx.log_seq()?;
+
+  db.put(&1, &2)?;
+  {
+    let tx = tx.w()?;
+    tx.db(&db).put(&1, &3)?;
+    tx.abort();
+  }
+  db.rm1(&1, &2)?;
+
+  let li = tx.changes_since(seq)?;
+  assert_eq!(li.len(), 2);
+  assert_eq!(li[0].0, seq + 1);
+  assert_eq!(li[1].0, seq + 2);
+  let (put, rm) = (&li[0].1, &li[1].1);
+  assert_eq!(put.id, 1);
+  assert_eq!(*Owned::<u64>::from_bytes(&put.key)?, 1);
+  assert_eq!(put.old, None);
+  assert_eq!(*Owned::<u64>::from_bytes(put.new.as_ref().unwrap())?, 2);
+  assert_eq!(rm.old, put.new);
+  assert_eq!(rm.new, None);
+
+  assert!(tx.truncate_log(seq + 1)? >= 1);
+  assert_eq!(tx.changes_since(0)?.len(), 1);
+  assert_eq!(tx.log_seq()?, seq + 2);
+  Ok(())
+}
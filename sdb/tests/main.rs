@@ -1,7 +1,12 @@
 mod db;
 use anyhow::Result;
-use db::{Data, Data2, Hash, DB0, DB1, DB2, DB3, DB4, DB5, TX};
-use sdb::UnsizedStorable;
+use db::{
+  Data, Data2, Data3, Flag, Hash, Raw12, Raw8, State, DB0, DB1, DB10, DB11, DB12, DB2, DB3, DB4,
+  DB5, DB6, DB7, DB7_RAW, DB8, DB8_RAW, DB9, TX,
+};
+use sdb::{Error, Owned, UnsizedStorable};
+use std::mem::size_of;
+use std::time::Duration;
 
 #[test]
 fn main() -> Result<()> {
@@ -73,6 +78,20 @@ fn main() -> Result<()> {
       println!("> {:?} {:?}", k, v)
     }
 
+    println!("- db2 : read [u8] value as u32 without copy");
+    db2.put(&3, &7u32.to_ne_bytes()[..])?;
+    assert_eq!(db2.one_view::<u32>(&3)?.as_deref(), Some(&7));
+    // checked on the bytes , no bool of 2 is made
+    db2.put(&4, &[1, 2][..])?;
+    assert!(matches!(
+      db2.one_view::<[bool; 2]>(&4),
+      Err(Error::Corruption(_))
+    ));
+    assert!(matches!(
+      sdb::view_slice::<bool>(&[0, 1, 2]),
+      Err(Error::Corruption(_))
+    ));
+
     let mut db3 = tx.db(&DB3);
     db3.put(&[1, 2][..], &[1, 2, 3][..])?;
 
@@ -106,9 +125,179 @@ fn main() -> Result<()> {
       let (k, v) = entry?;
       println!("> {:?} {:?}", k, Data2::from(v))
     }
+
+    let mut db6 = tx.db(&DB6);
+    for id in [1, 3, 2] {
+      db6.put(
+        &Data3 {
+          id,
+          hash: [0, 0, 0],
+        },
+        &id,
+      )?;
+    }
+    println!("- print all key db6 ( order by id desc )");
+    let mut ids = vec![];
+    for entry in db6.iter(None, None)? {
+      let (k, v) = entry?;
+      println!("> {:?} {:?}", k, v);
+      ids.push(k.id);
+    }
+    assert_eq!(ids, [3, 2, 1]);
+
+    let mut db7 = tx.db(&DB7);
+    db7.upsert(&1, &State::Run(9))?;
+    db7.upsert(&2, &State::Stop)?;
+    println!("- print all key db7");
+    for entry in db7.iter(None, None)? {
+      let (k, v) = entry?;
+      println!("> {:?} {:?}", k, v)
+    }
+    assert_eq!(db7.one(&1)?, Some(&State::Run(9)));
+
+    println!("- db7 : an unknown discriminant is Error::Corruption");
+    assert_eq!(size_of::<State>(), size_of::<Raw8>());
+    let mut raw7 = tx.db(&DB7_RAW);
+    // the repr(u8) tag is the first byte , 9 is no variant
+    raw7.put(&100, &Raw8([9, 0, 0, 0, 0, 0, 0, 0]))?;
+    let db7 = tx.db(&DB7);
+    assert!(matches!(db7.one(&100), Err(Error::Corruption(_))));
+    assert!(matches!(
+      db7.iter(&100, None)?.next(),
+      Some(Err(Error::Corruption(_)))
+    ));
+    raw7.rm(&100)?;
+
+    let mut db8 = tx.db(&DB8);
+    let flag = Flag {
+      on: true,
+      state: State::Init,
+    };
+    db8.upsert(&1, &flag)?;
+    assert_eq!(db8.one(&1)?, Some(&flag));
+
+    println!("- db8 : a bool byte of 2 or an unknown state is Error::Corruption");
+    assert_eq!(size_of::<Flag>(), size_of::<Raw12>());
+    let base = &flag as *const Flag as usize;
+    let on = &flag.on as *const bool as usize - base;
+    let state = &flag.state as *const State as usize - base;
+    let mut bad_on = [0; 12];
+    bad_on[on] = 2;
+    let mut bad_state = [0; 12];
+    bad_state[on] = 1;
+    bad_state[state] = 9;
+    let mut raw8 = tx.db(&DB8_RAW);
+    raw8.put(&100, &Raw12(bad_on))?;
+    raw8.put(&101, &Raw12(bad_state))?;
+    let db8 = tx.db(&DB8);
+    for k in [100, 101] {
+      assert!(matches!(db8.one(&k), Err(Error::Corruption(_))));
+      assert!(matches!(
+        db8.iter(&k, None)?.next(),
+        Some(Err(Error::Corruption(_)))
+      ));
+    }
+    raw8.rm(&100)?;
+    raw8.rm(&101)?;
+
+    let mut db9 = tx.db(&DB9);
+    let data = Data {
+      hash: [1, 2, 3],
+      id: 42,
+    };
+    db9.upsert(&1, &data)?;
+    db9.upsert(&2, &data)?;
+    assert_eq!(db9.by_index("id", &42u64)?.len(), 2);
+    db9.rm(&1)?;
+    assert_eq!(db9.by_index("id", &42u64)?, vec![(&2, &data)]);
+    db9.upsert(&2, &Data { id: 7, ..data })?;
+    assert!(db9.by_index("id", &42u64)?.is_empty());
+    assert_eq!(db9.rebuild_index("id")?, 1);
+    assert_eq!(db9.by_index("id", &7u64)?.len(), 1);
+    assert!(db9.by_index("email", &7u64).is_err());
+
+    let mut db10 = tx.db(&DB10);
+    db10.rm(&1)?;
+    db10.rm(&2)?;
+    db10.put(&1, &data)?;
+    db10.put(&1, &data)?;
+    db10.upsert(&1, &data)?;
+    assert!(matches!(
+      db10.put(&2, &data),
+      Err(sdb::Error::ConstraintViolation(_))
+    ));
+    assert!(matches!(
+      db10.upsert(&2, &data),
+      Err(sdb::Error::ConstraintViolation(_))
+    ));
+    assert_eq!(db10.one(&2)?, None);
     //write tx will auto commit when drop
   }
 
+  println!("# ttl");
+  DB11.rm(&1)?;
+  DB11.rm(&2)?;
+  DB11.put_with_ttl(&1, &1, Duration::ZERO)?;
+  DB11.put_with_ttl(&2, &2, Duration::from_secs(3600))?;
+  assert_eq!(DB11.one(&1)?, None);
+  assert_eq!(DB11.one(&2)?, Some(&2));
+  assert_eq!(DB11.iter(&1, None)?.count(), 1);
+  assert!(TX.expire_now()? >= 1);
+  DB11.put(&1, &5)?;
+  assert_eq!(DB11.one(&1)?, Some(&5));
+  assert!(DB0.put_with_ttl(&1, &1, Duration::ZERO).is_err());
+
+  println!("# watch");
+  let all = TX.subscribe();
+  let watch = DB12.watch(2u64..);
+  DB12.rm(&1)?;
+  DB12.rm(&2)?;
+  while all.try_recv().is_ok() {}
+  while watch.try_recv().is_ok() {}
+  DB12.put(&1, &1)?;
+  DB12.upsert(&2, &2)?;
+  {
+    let tx = TX.w()?;
+    tx.db(&DB12).put(&3, &3)?;
+    tx.abort();
+  }
+  assert_eq!(DB12.one(&3)?, None);
+  let change = watch.try_recv()?;
+  assert_eq!(*Owned::<u64>::from_bytes(&change.key)?, 2);
+  assert_eq!(change.old, None);
+  assert_eq!(*Owned::<u64>::from_bytes(&change.new.unwrap())?, 2);
+  // too short , or no State
+  assert!(matches!(
+    Owned::<u64>::from_bytes(&[1, 2]),
+    Err(Error::Corruption(_))
+  ));
+  assert!(matches!(
+    Owned::<State>::from_bytes(&[9, 0, 0, 0, 0, 0, 0, 0]),
+    Err(Error::Corruption(_))
+  ));
+  assert!(watch.try_recv().is_err());
+  assert_eq!(all.try_iter().count(), 2);
+
+  println!("# stats");
+  let stats = TX.stats()?;
+  let db12 = stats.trees.iter().find(|t| t.id == 16).unwrap();
+  assert_eq!(db12.entries, 2);
+  assert_eq!((db12.depth, db12.leaf_pages), (1, 1));
+  assert!(db12.fill > 0.0 && db12.fill < 1.0);
+  assert!(stats.trees.iter().any(|t| t.id == 10));
+
+  let mut dot = vec![];
+  TX.r()?.db(&DB12).to_dot(&mut dot)?;
+  let dot = String::from_utf8(dot)?;
+  assert!(dot.starts_with("digraph tree16 {"));
+  assert!(dot.contains("| 2 entries |"));
+
+  println!("# check");
+  let report = TX.check()?;
+  assert!(report.ok(), "{:?}", report.problems);
+  assert_eq!(report.trees, stats.trees.len());
+  assert!(report.entries >= db12.entries);
+
   {
     println!("# read transaction");
 
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use sdb::{Db, Error, Tx, TxArgs};
+use std::fs;
+
+#[test]
+fn read_only() -> Result<()> {
+  let tx = Tx::temp(&[TxArgs::InitSize(1 << 16)]);
+  let db: Db<u64, u64> = tx.db(1);
+  db.put(&1, &2)?;
+  // a copy , the file of tx stays open
+  let dir = tx.file().parent().unwrap();
+  fs::copy(tx.file(), dir.join("ro"))?;
+  let len = fs::metadata(dir.join("ro"))?.len();
+
+  let tx = Tx::new(dir, &[TxArgs::Filename("ro"), TxArgs::ReadOnly]);
+  let roots = tx.roots()?;
+  let db: Db<u64, u64> = tx.db(1);
+  assert_eq!(db.one(&1)?, Some(&2));
+  assert!(matches!(tx.w(), Err(Error::ReadOnly)));
+  assert!(matches!(db.put(&3, &3), Err(Error::ReadOnly)));
+  // a tree missing from the file reads as empty
+  let missing: Db<u64, u64> = tx.db(2);
+  assert_eq!(missing.one(&1)?, None);
+  assert_eq!(missing.iter(None, None)?.count(), 0);
+  assert_eq!(missing.key_iter(&1)?.count(), 0);
+  assert!(!missing.exist(&1, &2)?);
+  assert_eq!(tx.roots()?, roots);
+  assert_eq!(fs::metadata(dir.join("ro"))?.len(), len);
+
+  assert!(matches!(
+    Tx::open(dir, &[TxArgs::Filename("none"), TxArgs::ReadOnly]),
+    Err(Error::Io(_))
+  ));
+  assert!(!dir.join("none").exists());
+  Ok(())
+}
+
+#[test]
+fn read_only_shared() -> Result<()> {
+  let tx = Tx::temp(&[TxArgs::InitSize(1 << 16)]);
+  let db: Db<u64, u64> = tx.db(1);
+  db.put(&1, &2)?;
+  let dir = tx.file().parent().unwrap();
+  let name = tx.file().file_name().unwrap().to_str().unwrap();
+
+  // the same file as tx , still open : the readers wait neither for it nor for each other
+  let a = Tx::new(dir, &[TxArgs::Filename(name), TxArgs::ReadOnly]);
+  let b = Tx::new(dir, &[TxArgs::Filename(name), TxArgs::ReadOnly]);
+  let da: Db<u64, u64> = a.db(1);
+  let db_b: Db<u64, u64> = b.db(1);
+  assert_eq!(da.one(&1)?, Some(&2));
+  assert_eq!(db_b.one(&1)?, Some(&2));
+  assert!(matches!(da.put(&1, &3), Err(Error::ReadOnly)));
+  db.put(&3, &4)?;
+  assert_eq!(db.one(&3)?, Some(&4));
+  Ok(())
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use sdb::{read_changes, write_changes, Db, Error, Replica, Tx, TxArgs};
+use std::time::Duration;
+
+#[test]
+fn replica() -> Result<()> {
+  let primary = Tx::temp(&[TxArgs::Log(0)]);
+  let tx = Tx::memory(1 << 21);
+  let db: Db<u64, u64> = primary.db(1);
+  let replica_db: Db<u64, u64> = tx.db(1);
+  let mut replica = Replica::new(&tx, 2)?;
+  db.put(&1, &1)?;
+  db.upsert(&1, &2)?;
+  db.put(&3, &3)?;
+  assert!(replica.lag(&primary)? >= 3);
+
+  replica.follow(&primary)?;
+  assert_eq!(replica.lag(&primary)?, 0);
+  assert_eq!(replica_db.one(&1)?, Some(&2));
+  assert!(replica.check(&primary)?.is_empty());
+
+  // the same over a pipe
+  db.rm(&3)?;
+  let mut pipe = vec![];
+  write_changes(&mut pipe, &primary.changes_since(replica.seq())?)?;
+  assert_eq!(replica.apply(&read_changes(&mut &pipe[..])?)?, 1);
+  assert_eq!(replica_db.one(&3)?, None);
+  assert!(replica.check(&primary)?.is_empty());
+  Ok(())
+}
+
+#[test]
+fn replica_index_ttl() -> Result<()> {
+  let primary = Tx::temp(&[TxArgs::Log(0)]);
+  let tx = Tx::memory(1 << 21);
+  let db: Db<u64, u64> = primary
+    .db(1)
+    .with_index("v", 3, |v: &u64| *v)
+    .with_ttl(4, 5);
+  let replica_db: Db<u64, u64> = tx.db(1).with_index("v", 3, |v: &u64| *v).with_ttl(4, 5);
+  let mut replica = Replica::new(&tx, 2)?;
+  db.put(&1, &10)?;
+  db.upsert(&1, &11)?;
+  db.put_with_ttl(&2, &20, Duration::ZERO)?;
+
+  replica.follow(&primary)?;
+  assert_eq!(replica_db.by_index("v", &11u64)?, vec![(&1, &11)]);
+  assert!(replica_db.by_index("v", &10u64)?.is_empty());
+  // the deadline came with the put
+  assert_eq!(tx.expire_now()?, 1);
+  assert!(replica_db.by_index("v", &20u64)?.is_empty());
+
+  // and the expiry of the primary
+  db.put_with_ttl(&3, &30, Duration::ZERO)?;
+  primary.expire_now()?;
+  replica.follow(&primary)?;
+  assert!(replica_db.by_index("v", &30u64)?.is_empty());
+  assert!(replica.check(&primary)?.is_empty());
+  Ok(())
+}
+
+#[test]
+fn replica_corrupt() -> Result<()> {
+  let primary = Tx::temp(&[TxArgs::Log(0)]);
+  let tx = Tx::memory(1 << 21);
+  let db: Db<u64, u64> = primary.db(1);
+  let replica_db: Db<u64, u64> = tx.db(1);
+  let mut replica = Replica::new(&tx, 2)?;
+  db.put(&1, &1)?;
+  db.put(&2, &2)?;
+
+  // a value of 3 bytes in a tree of u64 : nothing is applied
+  let mut changes = primary.changes_since(0)?;
+  changes[1].1.new = Some(vec![1, 2, 3]);
+  assert!(matches!(replica.apply(&changes), Err(Error::Corruption(_))));
+  assert_eq!(replica.seq(), 0);
+  assert_eq!(replica_db.one(&1)?, None);
+  changes[1].1.id = 9;
+  assert!(matches!(
+    replica.apply(&changes),
+    Err(Error::UnknownTree(9))
+  ));
+
+  // a truncated stream , a count that is not there
+  let mut pipe = vec![];
+  write_changes(&mut pipe, &primary.changes_since(0)?)?;
+  assert!(read_changes(&mut &pipe[..pipe.len() - 1]).is_err());
+  pipe[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert!(read_changes(&mut &pipe[..]).is_err());
+
+  assert_eq!(replica.follow(&primary)?, 2);
+  assert_eq!(replica_db.one(&2)?, Some(&2));
+  Ok(())
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use sdb::{Db, Tx};
+
+#[test]
+fn savepoint() -> Result<()> {
+  let tx = Tx::temp(&[]);
+  let db: Db<u64, u64> = tx.db(1).with_index("v", 2, |v: &u64| *v);
+  let changes = tx.subscribe();
+  {
+    let w = tx.w()?;
+    w.db(&db).put(&1, &10)?;
+
+    let sp = w.savepoint()?;
+    let mut t = w.db(&db);
+    t.put(&2, &20)?;
+    t.rm(&1)?;
+    sp.rollback()?;
+
+    let mut t = w.db(&db);
+    assert_eq!(t.one(&1)?, Some(&10));
+    assert_eq!(t.one(&2)?, None);
+    assert!(t.by_index("v", &20u64)?.is_empty());
+
+    let sp = w.savepoint()?;
+    t.put(&3, &30)?;
+    sp.release();
+    t.put(&4, &40)?;
+  }
+  assert_eq!(db.one(&1)?, Some(&10));
+  assert_eq!(db.one(&3)?, Some(&30));
+  assert_eq!(db.by_index("v", &40u64)?, vec![(&4, &40)]);
+  assert_eq!(db.iter(None, None)?.count(), 3);
+  assert_eq!(changes.try_iter().count(), 3);
+  Ok(())
+}
+
+#[test]
+fn savepoint_txdb() -> Result<()> {
+  let tx = Tx::temp(&[]);
+  let db: Db<u64, u64> = tx.db(1).with_index("v", 2, |v: &u64| *v);
+  let late: Db<u64, u64> = tx.db(3);
+  {
+    let w = tx.w()?;
+    let mut t = w.db(&db);
+    t.put(&1, &10)?;
+
+    let sp = w.savepoint()?;
+    t.put(&2, &20)?;
+    let mut l = w.db(&late);
+    l.put(&1, &1)?;
+    sp.rollback()?;
+
+    // same TxDb , the root it held was dropped by the rollback
+    assert_eq!(t.one(&2)?, None);
+    assert!(l.one(&1)?.is_none());
+    t.put(&3, &30)?;
+    t.rm(&1)?;
+    assert_eq!(t.iter(None, None)?.count(), 1);
+    assert_eq!(t.by_index("v", &30u64)?, vec![(&3, &30)]);
+
+    // the tree did not exist at the savepoint , writing makes it again
+    l.put(&2, &2)?;
+  }
+  assert_eq!(db.one(&1)?, None);
+  assert_eq!(db.one(&3)?, Some(&30));
+  assert!(db.by_index("v", &10u64)?.is_empty());
+  assert_eq!(late.iter(None, None)?.count(), 1);
+  assert_eq!(late.one(&2)?, Some(&2));
+  Ok(())
+}
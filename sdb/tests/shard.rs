@@ -0,0 +1,84 @@
+use anyhow::Result;
+use sdb::{Db, Error, Handle, ShardedTx, Tx, TxArgs};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn shard() -> Result<()> {
+  // the shards go in the temp dir of base , removed with it
+  let base = Tx::temp(&[]);
+  let dir = base.file().with_file_name("shard");
+  let sharded = ShardedTx::new(&dir, 8, 2, vec![]);
+
+  for tenant in 0..20u64 {
+    let key = tenant.to_be_bytes();
+    let db: Handle<u64, u64> = sharded.db(&key, |tx| tx.db(1))?;
+    db.page().put(&tenant, &(tenant * 10))?;
+    assert!(sharded.open().len() <= 2);
+  }
+  assert_eq!(sharded.shard(b"tenant"), sharded.shard(b"tenant"));
+
+  let mut found = 0;
+  for shard in 0..sharded.shards() {
+    let tx = sharded.tx(shard)?;
+    let db: Db<u64, u64> = tx.db(1);
+    for entry in db.iter(None, None)? {
+      let (k, v) = entry?;
+      assert_eq!(sharded.shard(&k.to_be_bytes()), shard);
+      assert_eq!(*v, k * 10);
+      found += 1;
+    }
+  }
+  assert_eq!(found, 20);
+
+  // in use shards are not closed
+  let held = (0..3)
+    .map(|n| sharded.tx(n))
+    .collect::<Result<Vec<_>, _>>()?;
+  assert_eq!(sharded.open().len(), 3);
+  drop(held);
+  sharded.tx(3)?;
+  assert_eq!(sharded.open().len(), 3);
+  Ok(())
+}
+
+#[test]
+fn shard_threads() -> Result<()> {
+  let base = Tx::temp(&[]);
+  let sharded = Arc::new(ShardedTx::new(
+    base.file().with_file_name("shard"),
+    4,
+    4,
+    vec![],
+  ));
+  // the threads that ask for a shard being opened wait for it : one Tx per shard
+  let li: Vec<_> = (0..16)
+    .map(|n| {
+      let sharded = sharded.clone();
+      thread::spawn(move || sharded.tx(n % 2).unwrap())
+    })
+    .collect();
+  for (n, t) in li.into_iter().enumerate() {
+    assert!(Arc::ptr_eq(&t.join().unwrap(), &sharded.tx(n % 2)?));
+  }
+  assert_eq!(sharded.open().len(), 2);
+  Ok(())
+}
+
+#[test]
+fn shard_open_error() -> Result<()> {
+  let base = Tx::temp(&[]);
+  // no file to open read only
+  let sharded = ShardedTx::new(
+    base.file().with_file_name("shard"),
+    2,
+    2,
+    vec![TxArgs::ReadOnly],
+  );
+  assert!(matches!(sharded.tx(0), Err(Error::Io(_))));
+  // the failed shard is not left opening
+  assert!(sharded.get(b"tenant").is_err());
+  assert!(sharded.tx(0).is_err());
+  assert!(sharded.open().is_empty());
+  Ok(())
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use sdb::{DbU, Error, Tx, TxArgs};
+use std::env;
+use std::fs;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn max_size() -> Result<()> {
+  let dir = env::temp_dir().join(format!("sdb-size-{}", process::id()));
+  let _ = fs::remove_dir_all(&dir);
+  let args = [TxArgs::InitSize(1 << 16), TxArgs::MaxSize(1 << 17)];
+  let v = [7u8; 1000];
+  let mut n = 0;
+  {
+    let tx = Tx::open(&dir, &args)?;
+    assert_eq!(tx.max_size(), Some(1 << 17));
+    assert_eq!(fs::metadata(tx.file())?.len(), 1 << 17);
+    let db: DbU<u64, [u8]> = tx.db(1);
+    let full = loop {
+      match db.put(&n, &v[..]) {
+        Ok(_) => n += 1,
+        Err(err) => break err,
+      }
+      assert!(fs::metadata(tx.file())?.len() <= 1 << 17);
+      assert!(n < 1000);
+    };
+    assert!(matches!(full, Error::Full(_)));
+    assert_eq!(db.one(&(n - 1))?.map(|v| v.len()), Some(1000));
+    assert_eq!(db.one(&n)?, None);
+    // the map of sanakirja keeps the growth , no commit passes until the next open
+    assert!(matches!(db.rm(&0), Err(Error::Full(_))));
+  }
+
+  let tx = Tx::open(&dir, &args)?;
+  assert_eq!(fs::metadata(tx.file())?.len(), 1 << 17);
+  let db: DbU<u64, [u8]> = tx.db(1);
+  assert_eq!(db.one(&(n - 1))?.map(|v| v.len()), Some(1000));
+  // no page is allocated past the end of the file , rm commits at the cap
+  assert_eq!(db.rm(&0)?, 1);
+  db.put(&0, &v[..])?;
+
+  let called = Arc::new(AtomicUsize::new(0));
+  let counter = called.clone();
+  tx.on_full(move |len| {
+    counter.fetch_add(1, Ordering::Relaxed);
+    Some(len * 2)
+  });
+  // until a commit grows the file again
+  while called.load(Ordering::Relaxed) == 0 {
+    db.put(&n, &v[..])?;
+    n += 1;
+    assert!(n < 2000);
+  }
+  assert_eq!(db.one(&(n - 1))?.map(|v| v.len()), Some(1000));
+  assert_eq!(called.load(Ordering::Relaxed), 1);
+  assert!(tx.max_size().unwrap() > 1 << 17);
+
+  // a WriteTx that fails to commit on drop keeps its error
+  tx.on_full(|_| None);
+  {
+    let w = tx.w()?;
+    let mut db = w.db(&db);
+    for i in 0..2000 {
+      db.put(&(n + i), &v[..])?;
+    }
+  }
+  assert!(matches!(tx.take_drop_error(), Some(Error::Full(_))));
+  assert!(tx.take_drop_error().is_none());
+  assert_eq!(db.one(&n)?, None);
+  drop(tx);
+  fs::remove_dir_all(&dir)?;
+  Ok(())
+}
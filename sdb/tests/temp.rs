@@ -0,0 +1,29 @@
+use anyhow::Result;
+use sdb::{Db, Tx, TxArgs};
+
+#[test]
+fn temp() -> Result<()> {
+  let tx = Tx::temp(&[TxArgs::Log(0)]);
+  let dir = tx.file().parent().unwrap().to_path_buf();
+  assert!(tx.file().exists());
+  assert_ne!(Tx::temp(&[]).file(), tx.file());
+  let db: Db<u64, u64> = tx.db(1);
+  db.put(&1, &1)?;
+  assert_eq!(tx.changes_since(0)?.len(), 1);
+  drop(db);
+  drop(tx);
+  assert!(!dir.exists());
+  Ok(())
+}
+
+#[test]
+fn memory() -> Result<()> {
+  let tx = Tx::memory(1 << 20);
+  let db: Db<u64, u64> = tx.db(1);
+  for i in 0..1000u64 {
+    db.put(&i, &i)?;
+  }
+  assert_eq!(db.one(&999)?, Some(&999));
+  assert!(tx.space().is_err());
+  Ok(())
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use sdb::{Db, Tx};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn ttl_kept() -> Result<()> {
+  let tx = Tx::temp(&[]);
+  let db: Db<u64, u64> = tx.db(1).with_ttl(2, 3);
+  db.put_with_ttl(&1, &1, Duration::ZERO)?;
+  db.put_with_ttl(&2, &2, Duration::ZERO)?;
+  {
+    let w = tx.w()?;
+    let mut t = w.db(&db);
+    // expired : the values and the deadline go , the new value has none
+    t.put(&1, &10)?;
+    t.upsert(&2, &20)?;
+  }
+  assert_eq!(db.one(&1)?, Some(&10));
+  assert_eq!(db.one(&2)?, Some(&20));
+
+  db.put_with_ttl(&3, &3, Duration::from_millis(50))?;
+  db.upsert(&3, &30)?;
+  db.put_with_ttl(&4, &4, Duration::from_millis(50))?;
+  db.put(&4, &40)?;
+  db.put_with_ttl(&5, &5, Duration::from_secs(3600))?;
+  db.rm(&5)?;
+  db.put(&5, &50)?;
+  assert_eq!(db.one(&3)?, Some(&30));
+  thread::sleep(Duration::from_millis(100));
+  assert_eq!(db.one(&3)?, None);
+  assert_eq!(db.one(&4)?, None);
+  assert_eq!(db.one(&5)?, Some(&50));
+  assert_eq!(tx.expire_now()?, 2);
+  assert_eq!(db.iter(None, None)?.count(), 3);
+  Ok(())
+}
+
+#[test]
+fn expire_every() -> Result<()> {
+  let tx = Arc::new(Tx::temp(&[]));
+  let db: Db<u64, u64> = tx.db(1).with_ttl(2, 3);
+  db.put_with_ttl(&1, &1, Duration::ZERO)?;
+  drop(db);
+  let errors = Arc::new(AtomicUsize::new(0));
+  let expirer = {
+    let errors = errors.clone();
+    tx.expire_every(Duration::from_millis(1), move |_| {
+      errors.fetch_add(1, Ordering::Relaxed);
+    })
+  };
+  thread::sleep(Duration::from_millis(50));
+  assert_eq!(tx.expire_now()?, 0);
+  // the thread does not keep the Tx
+  let weak = Arc::downgrade(&tx);
+  drop(tx);
+  thread::sleep(Duration::from_millis(50));
+  assert!(weak.upgrade().is_none());
+  expirer.stop();
+  assert_eq!(errors.load(Ordering::Relaxed), 0);
+  Ok(())
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use sdb::{write_changes, Change, Coordinator, Db, DbU, Error, Owned, Tx};
+
+#[test]
+fn two_phase() -> Result<()> {
+  let journal = Tx::temp(&[]);
+  let hot = Tx::temp(&[]);
+  let cold = Tx::temp(&[]);
+  let recent: Db<u64, u64> = hot.db(1).with_index("v", 2, |v: &u64| *v);
+  let archive: DbU<u64, [u8]> = cold.db(1);
+  recent.put(&1, &10)?;
+
+  let seq = {
+    let coordinator = Coordinator::new(&journal, 9, vec![&hot, &cold])?;
+    let mut intent = coordinator.begin();
+    intent.rm1(&recent, &1, &10)?;
+    intent.put(&archive, &1, &b"10"[..])?;
+    intent.commit()?
+  };
+  assert_eq!(seq, 1);
+  assert_eq!(recent.one(&1)?, None);
+  assert!(recent.by_index("v", &10u64)?.is_empty());
+  assert_eq!(archive.one(&1)?, Some(&b"10"[..]));
+
+  // a new coordinator has nothing to recover and keeps counting
+  let coordinator = Coordinator::new(&journal, 9, vec![&hot, &cold])?;
+  assert_eq!(coordinator.recover()?, 0);
+  assert_eq!(coordinator.begin().commit()?, 0);
+  let mut intent = coordinator.begin();
+  intent.put(&recent, &2, &20)?;
+  assert_eq!(intent.commit()?, 2);
+  assert_eq!(recent.one(&2)?, Some(&20));
+  assert_eq!(recent.by_index("v", &20u64)?, vec![(&2, &20)]);
+  assert_eq!(archive.iter(None, None)?.count(), 1);
+
+  let other = Tx::memory(1 << 20);
+  let db: Db<u64, u64> = other.db(1);
+  assert!(matches!(
+    coordinator.begin().put(&db, &1, &1),
+    Err(Error::NotAPart)
+  ));
+  Ok(())
+}
+
+#[test]
+fn two_phase_abort() -> Result<()> {
+  let journal = Tx::temp(&[]);
+  let hot = Tx::temp(&[]);
+  let cold = Tx::temp(&[]);
+  let recent: Db<u64, u64> = hot.db(1);
+  let archive: Db<u64, u64> = cold.db(1).with_unique("v", 2, |v: &u64| *v);
+  archive.put(&1, &10)?;
+  let coordinator = Coordinator::new(&journal, 9, vec![&hot, &cold])?;
+
+  // the unique check of a part fails in prepare : nothing is written anywhere
+  let mut intent = coordinator.begin();
+  intent.put(&recent, &2, &20)?;
+  intent.put(&archive, &2, &10)?;
+  assert!(matches!(
+    intent.commit(),
+    Err(Error::ConstraintViolation(_))
+  ));
+  assert_eq!(recent.one(&2)?, None);
+  assert_eq!(archive.one(&2)?, None);
+  assert_eq!(coordinator.recover()?, 0);
+
+  // a journaled commit that was not applied blocks the next ones until recover
+  let log: DbU<u64, [u8]> = journal.db(9);
+  let mut pending = vec![];
+  write_changes(
+    &mut pending,
+    &[(
+      0,
+      Change {
+        id: 1,
+        key: Owned::new(&3u64).bytes().to_vec(),
+        old: None,
+        new: Some(Owned::new(&30u64).bytes().to_vec()),
+      },
+    )],
+  )?;
+  log.rm(&0)?;
+  log.put(&0, &1u64.to_le_bytes()[..])?;
+  log.put(&1, &pending[..])?;
+  let mut intent = coordinator.begin();
+  intent.put(&recent, &4, &40)?;
+  assert!(matches!(intent.commit(), Err(Error::Pending(1))));
+  assert_eq!(recent.one(&4)?, None);
+
+  assert_eq!(coordinator.recover()?, 1);
+  assert_eq!(recent.one(&3)?, Some(&30));
+  let mut intent = coordinator.begin();
+  intent.put(&recent, &4, &40)?;
+  assert_eq!(intent.commit()?, 2);
+  assert_eq!(recent.one(&4)?, Some(&40));
+  Ok(())
+}
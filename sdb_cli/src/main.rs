@@ -0,0 +1,286 @@
+mod out;
+mod ty;
+
+use out::{Out, Val};
+use sdb::{Problem, Tx, TxArgs};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::exit;
+use ty::{tree, Kind};
+
+const USAGE: &str = "usage : sdb-cli <dir> <command> [options]
+
+commands :
+  roots                 id and root page of every tree in the file
+  stats                 pages and entries of every tree with a --tree
+  dump <id>             entries of a tree , see --limit
+  get <id> <key>        values of a key , bytes keys as text or 0x hex
+  dot <id>              page structure of a tree as graphviz
+  check                 integrity report of every tree with a --tree , exit code 1 on problems
+  compact <new_file>    rewrite the file densely , every root must have a --tree
+
+options :
+  --json                json instead of a table
+  --file <name>         file name in dir , default sdb
+  --key-type <type>     key type of dump / get / dot : u64 u32 i64 bytes , default u64
+  --value-type <type>   value type of dump / get / dot , default bytes
+  --tree <id:key:value[:unsized]>
+                        types of one tree , unsized for a DbU of sized types
+  --limit <n>           dump at most n entries , default 100";
+
+struct Args {
+  dir: String,
+  cmd: Vec<String>,
+  file: Option<String>,
+  json: bool,
+  key: Kind,
+  value: Kind,
+  trees: BTreeMap<usize, (Kind, Kind, bool)>,
+  limit: usize,
+}
+
+fn parse() -> Result<Args, String> {
+  let mut pos = vec![];
+  let mut args = Args {
+    dir: String::new(),
+    cmd: vec![],
+    file: None,
+    json: false,
+    key: Kind::U64,
+    value: Kind::Bytes,
+    trees: BTreeMap::new(),
+    limit: 100,
+  };
+  let mut it = std::env::args().skip(1);
+  while let Some(arg) = it.next() {
+    let mut val = || it.next().ok_or(format!("{} needs a value", arg));
+    match arg.as_str() {
+      "-h" | "--help" => return Err(String::new()),
+      "--json" => args.json = true,
+      "--file" => args.file = Some(val()?),
+      "--key-type" => args.key = val()?.parse()?,
+      "--value-type" => args.value = val()?.parse()?,
+      "--limit" => args.limit = val()?.parse().map_err(|e| format!("--limit : {}", e))?,
+      "--tree" => {
+        let spec = val()?;
+        let li: Vec<&str> = spec.split(':').collect();
+        let up = match li.get(3) {
+          None => false,
+          Some(&"unsized") => true,
+          Some(_) => return Err(format!("--tree {} : expected id:key:value[:unsized]", spec)),
+        };
+        if li.len() < 3 || li.len() > 4 {
+          return Err(format!("--tree {} : expected id:key:value[:unsized]", spec));
+        }
+        let id = li[0]
+          .parse()
+          .map_err(|e| format!("--tree {} : {}", spec, e))?;
+        args.trees.insert(id, (li[1].parse()?, li[2].parse()?, up));
+      }
+      s if s.starts_with("--") => return Err(format!("unknown option {}", s)),
+      _ => pos.push(arg),
+    }
+  }
+  if pos.len() < 2 {
+    return Err(String::new());
+  }
+  args.dir = pos.remove(0);
+  args.cmd = pos;
+  Ok(args)
+}
+
+fn id(s: Option<&String>) -> Result<usize, Box<dyn Error>> {
+  let s = s.ok_or("missing tree id")?;
+  Ok(s.parse().map_err(|e| format!("tree id {} : {}", s, e))?)
+}
+
+impl Args {
+  fn types(&self, id: usize) -> (Kind, Kind, bool) {
+    self
+      .trees
+      .get(&id)
+      .copied()
+      .unwrap_or((self.key, self.value, false))
+  }
+
+  // roots without a --tree are skipped : read with guessed types they would look corrupted ;
+  // returns their ids
+  fn declare(&self, tx: &Tx, roots: &[(usize, u64)]) -> Val {
+    let mut skipped = vec![];
+    for (id, _) in roots {
+      match self.trees.get(id) {
+        Some(&(k, v, up)) => tree(*id, k, v, up).declare(tx),
+        None => skipped.push(Val::num(id)),
+      }
+    }
+    Val::Arr(skipped)
+  }
+}
+
+fn problem(p: &Problem) -> Vec<Val> {
+  let (kind, id, detail) = match p {
+    Problem::Order { id, entry } => ("order", Val::num(id), format!("entry {}", entry)),
+    Problem::Corruption { id, entry, ty } => (
+      "corruption",
+      Val::num(id),
+      format!("entry {} : {}", entry, ty),
+    ),
+    Problem::SharedPage { offset, ids } => (
+      "shared page",
+      Val::num(ids.1),
+      format!("page {} also in tree {}", offset, ids.0),
+    ),
+    Problem::FreePage { id, offset } => ("free page", Val::num(id), format!("page {}", offset)),
+    Problem::OutOfFile { id, offset } => ("out of file", Val::num(id), format!("page {}", offset)),
+    // no tree : the free list of the file
+    Problem::FreeList { offset } => ("free list", Val::str("-"), format!("page {}", offset)),
+    Problem::Unreadable { id, error } => ("unreadable", Val::num(id), error.clone()),
+  };
+  vec![Val::str(kind), id, Val::Str(detail)]
+}
+
+fn run(args: Args) -> Result<bool, Box<dyn Error>> {
+  let out = Out { json: args.json };
+  let mut tx_args = vec![];
+  if let Some(file) = &args.file {
+    tx_args.push(TxArgs::Filename(file));
+  }
+  // only compact writes
+  if args.cmd[0] != "compact" {
+    tx_args.push(TxArgs::ReadOnly);
+  }
+  let mut tx = Tx::open(&args.dir, &tx_args)?;
+  let roots = tx.roots()?;
+  let exist = |id: usize| -> Result<usize, Box<dyn Error>> {
+    if roots.iter().any(|(i, _)| *i == id) {
+      Ok(id)
+    } else {
+      Err(format!("no tree {} in the file", id).into())
+    }
+  };
+
+  match args.cmd[0].as_str() {
+    "roots" => {
+      let rows = roots
+        .iter()
+        .map(|(id, offset)| vec![Val::num(id), Val::num(offset)])
+        .collect();
+      out.print("roots", &["id", "page"], rows, vec![]);
+    }
+    "stats" => {
+      let skipped = args.declare(&tx, &roots);
+      let stats = tx.stats()?;
+      let rows = stats
+        .trees
+        .iter()
+        .map(|t| {
+          vec![
+            Val::num(t.id),
+            Val::num(t.entries),
+            Val::num(t.depth),
+            Val::num(t.leaf_pages),
+            Val::num(t.internal_pages),
+            Val::num(t.bytes),
+            Val::Num(format!("{:.3}", t.fill)),
+          ]
+        })
+        .collect();
+      out.print(
+        "trees",
+        &[
+          "id", "entries", "depth", "leaf", "internal", "bytes", "fill",
+        ],
+        rows,
+        vec![
+          ("file", Val::num(stats.file)),
+          ("free_pages", Val::num(stats.free_pages)),
+          ("skipped", skipped),
+        ],
+      );
+    }
+    "dump" => {
+      let id = exist(id(args.cmd.get(1))?)?;
+      let (k, v, up) = args.types(id);
+      let rows = tree(id, k, v, up).dump(&tx, args.limit)?;
+      out.print("entries", &["key", "value"], rows, vec![]);
+    }
+    "get" => {
+      let id = exist(id(args.cmd.get(1))?)?;
+      let key = args.cmd.get(2).ok_or("missing key")?;
+      let (k, v, up) = args.types(id);
+      let rows = tree(id, k, v, up).get(&tx, key)?;
+      out.print("entries", &["key", "value"], rows, vec![]);
+    }
+    "dot" => {
+      let id = exist(id(args.cmd.get(1))?)?;
+      let (k, v, up) = args.types(id);
+      tree(id, k, v, up).dot(&tx)?;
+    }
+    "check" => {
+      let skipped = args.declare(&tx, &roots);
+      let report = tx.check()?;
+      let rows = report.problems.iter().map(problem).collect();
+      out.print(
+        "problems",
+        &["problem", "tree", "detail"],
+        rows,
+        vec![
+          ("ok", Val::Bool(report.ok())),
+          ("trees", Val::num(report.trees)),
+          ("entries", Val::num(report.entries)),
+          ("pages", Val::num(report.pages)),
+          ("skipped", skipped),
+        ],
+      );
+      return Ok(report.ok());
+    }
+    "compact" => {
+      let path = args.cmd.get(1).ok_or("missing new file")?;
+      // a tree read with the wrong types would be copied wrong
+      if let Some((id, _)) = roots.iter().find(|(id, _)| !args.trees.contains_key(id)) {
+        return Err(
+          format!(
+            "tree {} has no --tree , compact needs the types of every tree",
+            id
+          )
+          .into(),
+        );
+      }
+      args.declare(&tx, &roots);
+      let reclaimed = tx.compact_into(path)?;
+      let space = tx.space()?;
+      out.print(
+        "",
+        &[],
+        vec![],
+        vec![
+          ("reclaimed", Val::num(reclaimed)),
+          ("file", Val::num(space.file)),
+        ],
+      );
+    }
+    cmd => return Err(format!("unknown command {}\n\n{}", cmd, USAGE).into()),
+  }
+  Ok(true)
+}
+
+fn main() {
+  let args = match parse() {
+    Ok(args) => args,
+    Err(err) => {
+      if !err.is_empty() {
+        eprintln!("{}\n", err);
+      }
+      eprintln!("{}", USAGE);
+      exit(2);
+    }
+  };
+  match run(args) {
+    Ok(true) => {}
+    Ok(false) => exit(1),
+    Err(err) => {
+      eprintln!("{}", err);
+      exit(2);
+    }
+  }
+}
@@ -0,0 +1,144 @@
+use std::fmt::Write;
+
+pub enum Val {
+  Num(String),
+  Str(String),
+  Bool(bool),
+  Arr(Vec<Val>),
+  Obj(Vec<(String, Val)>),
+}
+
+impl Val {
+  pub fn num<N: ToString>(n: N) -> Self {
+    Val::Num(n.to_string())
+  }
+
+  pub fn str<S: Into<String>>(s: S) -> Self {
+    Val::Str(s.into())
+  }
+
+  pub fn json(&self, out: &mut String) {
+    match self {
+      Val::Num(n) => out.push_str(n),
+      Val::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+      Val::Str(s) => {
+        out.push('"');
+        for c in s.chars() {
+          match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+          }
+        }
+        out.push('"');
+      }
+      Val::Arr(li) => {
+        out.push('[');
+        for (n, v) in li.iter().enumerate() {
+          if n > 0 {
+            out.push(',');
+          }
+          v.json(out);
+        }
+        out.push(']');
+      }
+      Val::Obj(li) => {
+        out.push('{');
+        for (n, (k, v)) in li.iter().enumerate() {
+          if n > 0 {
+            out.push(',');
+          }
+          Val::str(k.as_str()).json(out);
+          out.push(':');
+          v.json(out);
+        }
+        out.push('}');
+      }
+    }
+  }
+
+  // a table cell
+  pub fn text(&self) -> String {
+    match self {
+      Val::Num(s) | Val::Str(s) => s.clone(),
+      Val::Bool(b) => b.to_string(),
+      _ => {
+        let mut s = String::new();
+        self.json(&mut s);
+        s
+      }
+    }
+  }
+}
+
+pub struct Out {
+  pub json: bool,
+}
+
+impl Out {
+  // summary lines then a table ; as json an array of objects , or an object with the summary
+  // and the rows under name (none without a head)
+  pub fn print(&self, name: &str, head: &[&str], rows: Vec<Vec<Val>>, summary: Vec<(&str, Val)>) {
+    if self.json {
+      let rows = Val::Arr(
+        rows
+          .into_iter()
+          .map(|row| Val::Obj(head.iter().map(|h| h.to_string()).zip(row).collect()))
+          .collect(),
+      );
+      let val = if summary.is_empty() {
+        rows
+      } else {
+        let mut li: Vec<_> = summary
+          .into_iter()
+          .map(|(k, v)| (k.to_string(), v))
+          .collect();
+        if !head.is_empty() {
+          li.push((name.to_string(), rows));
+        }
+        Val::Obj(li)
+      };
+      let mut s = String::new();
+      val.json(&mut s);
+      println!("{}", s);
+      return;
+    }
+
+    for (k, v) in &summary {
+      println!("{}: {}", k, v.text());
+    }
+    if !summary.is_empty() && !rows.is_empty() {
+      println!();
+    }
+    if rows.is_empty() {
+      return;
+    }
+    let rows: Vec<Vec<String>> = rows
+      .iter()
+      .map(|row| row.iter().map(Val::text).collect())
+      .collect();
+    let mut width: Vec<usize> = head.iter().map(|h| h.len()).collect();
+    for row in &rows {
+      for (w, cell) in width.iter_mut().zip(row) {
+        *w = (*w).max(cell.chars().count());
+      }
+    }
+    let line = |row: &[String]| {
+      let li: Vec<String> = row
+        .iter()
+        .zip(&width)
+        .map(|(cell, w)| format!("{:<w$}", cell, w = w))
+        .collect();
+      println!("{}", li.join("  ").trim_end());
+    };
+    line(&head.iter().map(|h| h.to_string()).collect::<Vec<_>>());
+    line(&width.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>());
+    for row in &rows {
+      line(row);
+    }
+  }
+}
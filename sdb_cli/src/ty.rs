@@ -0,0 +1,187 @@
+use crate::out::Val;
+use sdb::btree::page_unsized::Page as UP;
+use sdb::btree::{BTreeMutPage, BTreePage};
+use sdb::{DbPage, Encode, Page, Storable, Tx, UnsizedStorable, Verify};
+use std::error::Error;
+use std::fmt::Write;
+use std::io;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+// the key / value types the cli can read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+  U64,
+  U32,
+  I64,
+  Bytes,
+}
+
+impl FromStr for Kind {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, String> {
+    Ok(match s {
+      "u64" => Kind::U64,
+      "u32" => Kind::U32,
+      "i64" => Kind::I64,
+      "bytes" => Kind::Bytes,
+      _ => return Err(format!("unknown type {} , expected u64 u32 i64 bytes", s)),
+    })
+  }
+}
+
+pub trait Ty: Storable + UnsizedStorable + PartialEq + Verify + Encode<Self> + 'static {
+  fn val(&self) -> Val;
+  fn parse(s: &str) -> Result<Box<Self>, String>;
+}
+
+macro_rules! ty_int {
+  ( $( $x:ty ),* ) => {
+    $(
+      impl Ty for $x {
+        fn val(&self) -> Val {
+          Val::num(self)
+        }
+        fn parse(s: &str) -> Result<Box<Self>, String> {
+          s.parse().map(Box::new).map_err(|e| format!("{} : {}", s, e))
+        }
+      }
+    )*
+  };
+}
+
+ty_int!(u64, u32, i64);
+
+// printable utf-8 as is , anything else as 0x hex
+impl Ty for [u8] {
+  fn val(&self) -> Val {
+    if let Ok(s) = std::str::from_utf8(self) {
+      if !s.starts_with("0x") && !s.chars().any(char::is_control) {
+        return Val::str(s);
+      }
+    }
+    let mut s = String::from("0x");
+    for b in self {
+      write!(s, "{:02x}", b).unwrap();
+    }
+    Val::Str(s)
+  }
+
+  fn parse(s: &str) -> Result<Box<Self>, String> {
+    match s.strip_prefix("0x") {
+      None => Ok(s.as_bytes().into()),
+      Some(hex) => {
+        if hex.len() % 2 != 0 {
+          return Err(format!("{} : odd number of hex digits", s));
+        }
+        (0..hex.len())
+          .step_by(2)
+          .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+          .collect::<Result<Vec<u8>, _>>()
+          .map(Vec::into_boxed_slice)
+          .map_err(|e| format!("{} : {}", s, e))
+      }
+    }
+  }
+}
+
+// a tree whose types are only known at run time
+pub trait Tree {
+  // register the tree in the Tx , for stats / check / compact
+  fn declare(&self, tx: &Tx);
+  fn dump(&self, tx: &Tx, limit: usize) -> Result<Vec<Vec<Val>>, Box<dyn Error>>;
+  fn get(&self, tx: &Tx, key: &str) -> Result<Vec<Vec<Val>>, Box<dyn Error>>;
+  fn dot(&self, tx: &Tx) -> Result<(), Box<dyn Error>>;
+}
+
+struct Typed<K: ?Sized, V: ?Sized, P> {
+  id: usize,
+  _kvp: PhantomData<(*const K, *const V, P)>,
+}
+
+impl<K: Ty + ?Sized, V: Ty + ?Sized, P: BTreeMutPage<K, V> + BTreePage<K, V> + 'static> Tree
+  for Typed<K, V, P>
+{
+  fn declare(&self, tx: &Tx) {
+    let _: DbPage<K, V, P, K, V> = tx.db(self.id);
+  }
+
+  fn dump(&self, tx: &Tx, limit: usize) -> Result<Vec<Vec<Val>>, Box<dyn Error>> {
+    let page: DbPage<K, V, P, K, V> = tx.db(self.id);
+    let r = tx.r()?;
+    let db = r.db(&page);
+    let mut rows = vec![];
+    for entry in db.iter(None, None)?.take(limit) {
+      let (k, v) = entry?;
+      rows.push(vec![k.val(), v.val()]);
+    }
+    Ok(rows)
+  }
+
+  fn get(&self, tx: &Tx, key: &str) -> Result<Vec<Vec<Val>>, Box<dyn Error>> {
+    let key = K::parse(key)?;
+    let page: DbPage<K, V, P, K, V> = tx.db(self.id);
+    let r = tx.r()?;
+    let db = r.db(&page);
+    let mut rows = vec![];
+    for entry in db.key_iter(&*key)? {
+      let (k, v) = entry?;
+      rows.push(vec![k.val(), v.val()]);
+    }
+    Ok(rows)
+  }
+
+  fn dot(&self, tx: &Tx) -> Result<(), Box<dyn Error>> {
+    let page: DbPage<K, V, P, K, V> = tx.db(self.id);
+    let r = tx.r()?;
+    r.db(&page).to_dot(io::stdout().lock())?;
+    Ok(())
+  }
+}
+
+macro_rules! sized {
+  ($id:expr, $up:expr, $k:ty, $v:ty) => {
+    if $up {
+      Box::new(Typed::<$k, $v, UP<$k, $v>> {
+        id: $id,
+        _kvp: PhantomData,
+      }) as Box<dyn Tree>
+    } else {
+      Box::new(Typed::<$k, $v, Page<$k, $v>> {
+        id: $id,
+        _kvp: PhantomData,
+      })
+    }
+  };
+}
+
+// [u8] only fits the unsized page
+macro_rules! unsized_page {
+  ($id:expr, $up:expr, $k:ty, $v:ty) => {
+    Box::new(Typed::<$k, $v, UP<$k, $v>> {
+      id: $id,
+      _kvp: PhantomData,
+    }) as Box<dyn Tree>
+  };
+}
+
+macro_rules! by_value {
+  ($page:ident, $id:expr, $v:expr, $up:expr, $k:ty) => {
+    match $v {
+      Kind::U64 => $page!($id, $up, $k, u64),
+      Kind::U32 => $page!($id, $up, $k, u32),
+      Kind::I64 => $page!($id, $up, $k, i64),
+      Kind::Bytes => unsized_page!($id, $up, $k, [u8]),
+    }
+  };
+}
+
+// up : the tree was declared as DbU ( btree::page_unsized::Page ) , forced by bytes
+pub fn tree(id: usize, k: Kind, v: Kind, up: bool) -> Box<dyn Tree> {
+  match k {
+    Kind::U64 => by_value!(sized, id, v, up, u64),
+    Kind::U32 => by_value!(sized, id, v, up, u32),
+    Kind::I64 => by_value!(sized, id, v, up, i64),
+    Kind::Bytes => by_value!(unsized_page, id, v, up, [u8]),
+  }
+}
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use sdb::{Db, DbU, Tx, TxArgs};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// exit code and stdout of sdb-cli on the copy "cli" of the file
+fn cli(dir: &Path, args: &[&str]) -> (i32, String) {
+  let out = Command::new(env!("CARGO_BIN_EXE_sdb-cli"))
+    .arg(dir)
+    .args(["--file", "cli"])
+    .args(args)
+    .output()
+    .unwrap();
+  (
+    out.status.code().unwrap(),
+    String::from_utf8(out.stdout).unwrap(),
+  )
+}
+
+#[test]
+fn commands() -> Result<()> {
+  let tx = Tx::temp(&[TxArgs::InitSize(1 << 16)]);
+  let bytes: DbU<[u8], [u8]> = tx.db(1);
+  bytes.put(&[1u8, 2][..], &b"v"[..])?;
+  bytes.put(&b"a\"b"[..], &[0u8][..])?;
+  let ints: Db<u64, u64> = tx.db(2);
+  ints.put(&7, &8)?;
+  // a copy , the file of tx stays locked
+  let dir = tx.file().parent().unwrap();
+  fs::copy(tx.file(), dir.join("cli"))?;
+  let tree = ["--tree", "1:bytes:bytes"];
+
+  println!("# hex key");
+  let (code, out) = cli(dir, &["get", "1", "0x0102", "--json", tree[0], tree[1]]);
+  assert_eq!(code, 0);
+  assert_eq!(out.trim(), r#"[{"key":"0x0102","value":"v"}]"#);
+  assert_eq!(cli(dir, &["get", "1", "0x012", tree[0], tree[1]]).0, 2);
+  assert_eq!(cli(dir, &["get", "1", "0xzz", tree[0], tree[1]]).0, 2);
+
+  println!("# json escape");
+  let (code, out) = cli(dir, &["get", "1", "a\"b", "--json", tree[0], tree[1]]);
+  assert_eq!(code, 0);
+  assert_eq!(out.trim(), r#"[{"key":"a\"b","value":"0x00"}]"#);
+
+  println!("# default types");
+  let (code, out) = cli(dir, &["dump", "2", "--json", "--value-type", "u64"]);
+  assert_eq!(code, 0);
+  assert_eq!(out.trim(), r#"[{"key":7,"value":8}]"#);
+  assert_eq!(cli(dir, &["dump", "3"]).0, 2);
+
+  println!("# roots");
+  let (code, out) = cli(dir, &["roots", "--json"]);
+  assert_eq!(code, 0);
+  assert!(out.contains(r#""id":1,"#) && out.contains(r#""id":2,"#));
+
+  println!("# missing file");
+  assert_eq!(cli(dir, &["roots", "--file", "none"]).0, 2);
+  assert!(!dir.join("none").exists());
+
+  println!("# stats and check skip the roots without --tree");
+  let (code, out) = cli(dir, &["stats", "--json", tree[0], tree[1]]);
+  assert_eq!(code, 0);
+  assert!(out.contains(r#""skipped":[2]"#), "{}", out);
+  assert!(out.contains(r#""id":1,"entries":2,"#), "{}", out);
+  assert!(!out.contains(r#""id":2,"#), "{}", out);
+  let (code, out) = cli(dir, &["check", "--json", tree[0], tree[1]]);
+  assert_eq!(code, 0);
+  assert!(out.contains(r#""ok":true"#), "{}", out);
+  assert!(out.contains(r#""skipped":[2]"#), "{}", out);
+
+  println!("# compact needs every --tree");
+  let new = dir.join("new");
+  let new = new.to_str().unwrap();
+  assert_eq!(cli(dir, &["compact", new, tree[0], tree[1]]).0, 2);
+  let (code, _) = cli(
+    dir,
+    &["compact", new, tree[0], tree[1], "--tree", "2:u64:u64"],
+  );
+  assert_eq!(code, 0);
+  let (_, out) = cli(dir, &["dump", "2", "--json", "--value-type", "u64"]);
+  assert_eq!(out.trim(), r#"[{"key":7,"value":8}]"#);
+  Ok(())
+}
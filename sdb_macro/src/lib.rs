@@ -3,22 +3,320 @@ use proc_macro::TokenStream;
 extern crate syn;
 #[macro_use]
 extern crate quote;
-use syn::{parse_macro_input, DeriveInput};
+use proc_macro2::TokenStream as TokenStream2;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DataEnum, DeriveInput, Fields, Lit, Meta, NestedMeta};
 
-#[proc_macro_derive(Sdb)]
+#[proc_macro_derive(Sdb, attributes(sdb))]
 pub fn sdb(input: TokenStream) -> TokenStream {
   let ast = parse_macro_input!(input as DeriveInput);
+  let expanded = match sdb_repr(&ast) {
+    Ok(expanded) => expanded,
+    Err(err) => err.to_compile_error(),
+  };
+  TokenStream::from(expanded)
+}
+
+fn sdb_repr(ast: &DeriveInput) -> syn::Result<TokenStream2> {
   let name = &ast.ident;
+  let attr = SdbAttr::parse(ast)?;
 
-  let expanded = quote! {
-    sdb::repr!(#name);
+  let cmp = match &attr.order_by {
+    None => None,
+    Some(order_by) => Some(compare(ast, order_by)?),
   };
-  TokenStream::from(expanded)
+  let verify = match &ast.data {
+    Data::Enum(e) => Some(verify_enum(ast, e)?),
+    Data::Struct(s) => match &s.fields {
+      // newtype : check the wrapped value
+      Fields::Unnamed(f) if f.unnamed.len() == 1 => Some(quote!(sdb::Verify::verify(&v.0))),
+      fields if attr.validate && !fields.is_empty() => {
+        let fields = fields.iter().enumerate().map(|(i, f)| match &f.ident {
+          Some(ident) => quote!(#ident),
+          None => {
+            let i = syn::Index::from(i);
+            quote!(#i)
+          }
+        });
+        Some(quote!(#(sdb::Verify::verify(&v.#fields))&&*))
+      }
+      _ => None,
+    },
+    Data::Union(_) => {
+      return Err(syn::Error::new(
+        name.span(),
+        "#[derive(Sdb)] is not supported on unions",
+      ))
+    }
+  };
+
+  Ok(match (cmp, verify) {
+    (None, None) => quote! {
+      sdb::repr!(#name);
+    },
+    (Some(cmp), None) => quote! {
+      sdb::repr!(#name, |a, b| #cmp);
+    },
+    (None, Some(verify)) => quote! {
+      sdb::repr!(#name, verify |v| #verify);
+    },
+    (Some(cmp), Some(verify)) => quote! {
+      sdb::repr!(#name, |a, b| #cmp, verify |v| #verify);
+    },
+  })
+}
+
+const REPR: [&str; 10] = [
+  "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize",
+];
+
+// the integer type of #[repr(u8)] , #[repr(C, u32)] ...
+fn enum_repr(ast: &DeriveInput) -> syn::Result<syn::Path> {
+  for attr in ast.attrs.iter().filter(|a| a.path.is_ident("repr")) {
+    if let Meta::List(list) = attr.parse_meta()? {
+      for nested in list.nested {
+        if let NestedMeta::Meta(Meta::Path(path)) = nested {
+          if REPR.iter().any(|r| path.is_ident(r)) {
+            return Ok(path);
+          }
+        }
+      }
+    }
+  }
+  Err(syn::Error::new(
+    ast.ident.span(),
+    "#[derive(Sdb)] on an enum needs an explicit #[repr(u8)] / #[repr(u16)] / #[repr(u32)] ...",
+  ))
+}
+
+// read the discriminant before the value is used , an unknown one is a corrupted page
+fn verify_enum(ast: &DeriveInput, e: &DataEnum) -> syn::Result<TokenStream2> {
+  let name = &ast.ident;
+  let repr = enum_repr(ast)?;
+
+  // discriminant = last explicit one + offset , literals are folded here
+  let mut base: Option<&syn::Expr> = None;
+  let mut lit = Some(0i128);
+  let mut offset = 0i128;
+  let other = if e.variants.len() > 1 {
+    quote!(_ => false,)
+  } else {
+    quote!()
+  };
+  let mut arms = vec![];
+  for variant in &e.variants {
+    if let Some((_, expr)) = &variant.discriminant {
+      base = Some(expr);
+      lit = int_lit(expr);
+      offset = 0;
+    }
+    let tag = match (lit, base) {
+      (Some(n), _) => {
+        let n = proc_macro2::Literal::i128_unsuffixed(n + offset);
+        quote!(#n)
+      }
+      (None, Some(expr)) => {
+        let offset = proc_macro2::Literal::i128_unsuffixed(offset);
+        quote!(((#expr) as #repr + #offset))
+      }
+      (None, None) => unreachable!(),
+    };
+    offset += 1;
+
+    let ident = &variant.ident;
+    let (pat, bind): (TokenStream2, Vec<TokenStream2>) = match &variant.fields {
+      Fields::Unit => (quote!(), vec![]),
+      Fields::Unnamed(f) => {
+        let bind: Vec<_> = (0..f.unnamed.len())
+          .map(|i| {
+            let i = format_ident!("f{}", i);
+            quote!(#i)
+          })
+          .collect();
+        (quote!((#(#bind),*)), bind)
+      }
+      Fields::Named(f) => {
+        let bind: Vec<_> = f
+          .named
+          .iter()
+          .map(|f| {
+            let i = f.ident.as_ref().unwrap();
+            quote!(#i)
+          })
+          .collect();
+        (quote!({ #(#bind),* }), bind)
+      }
+    };
+    let check = if bind.is_empty() {
+      quote!(true)
+    } else {
+      quote! {
+        match v {
+          #name::#ident #pat => #(sdb::Verify::verify(#bind))&&*,
+          #other
+        }
+      }
+    };
+    arms.push((tag, check));
+  }
+
+  let chain = arms
+    .into_iter()
+    .rev()
+    .fold(quote!(false), |next, (tag, check)| {
+      quote! {
+        if tag == #tag {
+          #check
+        } else {
+          #next
+        }
+      }
+    });
+
+  Ok(quote! {{
+    let tag = unsafe { core::ptr::read(v as *const #name as *const #repr) };
+    #chain
+  }})
 }
 
-/*
-fn sdb_repr(ast: &syn::MacroInput) -> quote::Tokens {
-  quote! {
+fn int_lit(expr: &syn::Expr) -> Option<i128> {
+  match expr {
+    syn::Expr::Lit(syn::ExprLit {
+      lit: Lit::Int(i), ..
+    }) => i.base10_parse().ok(),
+    syn::Expr::Unary(syn::ExprUnary {
+      op: syn::UnOp::Neg(_),
+      expr,
+      ..
+    }) => int_lit(expr).map(|n| -n),
+    syn::Expr::Paren(p) => int_lit(&p.expr),
+    _ => None,
   }
 }
-*/
+
+#[derive(Default)]
+struct SdbAttr {
+  order_by: Option<syn::LitStr>,
+  // check every field (bool, char, enum, NonZero ...) when read from disk
+  validate: bool,
+}
+
+impl SdbAttr {
+  fn parse(ast: &DeriveInput) -> syn::Result<Self> {
+    let mut attr = SdbAttr::default();
+    for meta in ast.attrs.iter().filter(|a| a.path.is_ident("sdb")) {
+      let list = match meta.parse_meta()? {
+        Meta::List(list) => list,
+        meta => return Err(syn::Error::new(meta.span(), "expected #[sdb(...)]")),
+      };
+      for nested in list.nested {
+        match nested {
+          NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("order_by") => match nv.lit {
+            Lit::Str(s) => attr.order_by = Some(s),
+            lit => {
+              return Err(syn::Error::new(
+                lit.span(),
+                "order_by expects a string like \"id, hash desc\"",
+              ))
+            }
+          },
+          NestedMeta::Meta(Meta::Path(path)) if path.is_ident("validate") => attr.validate = true,
+          nested => return Err(syn::Error::new(nested.span(), "unknown sdb attribute")),
+        }
+      }
+    }
+    Ok(attr)
+  }
+}
+
+// order_by = "id, hash desc" : compare the listed fields in order, then the remaining fields in
+// declaration order so that compare stays consistent with Eq
+fn compare(ast: &DeriveInput, order_by: &syn::LitStr) -> syn::Result<TokenStream2> {
+  let fields: Vec<TokenStream2> = match &ast.data {
+    Data::Struct(s) => match &s.fields {
+      Fields::Named(f) => f
+        .named
+        .iter()
+        .map(|f| {
+          let i = f.ident.as_ref().unwrap();
+          quote!(#i)
+        })
+        .collect(),
+      Fields::Unnamed(f) => (0..f.unnamed.len())
+        .map(|i| {
+          let i = syn::Index::from(i);
+          quote!(#i)
+        })
+        .collect(),
+      Fields::Unit => vec![],
+    },
+    _ => {
+      return Err(syn::Error::new(
+        order_by.span(),
+        "order_by is only supported on structs",
+      ))
+    }
+  };
+  let names: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+
+  let mut order = vec![];
+  for item in order_by.value().split(',') {
+    let mut words = item.split_whitespace();
+    let field = match words.next() {
+      Some(field) => field,
+      None => return Err(syn::Error::new(order_by.span(), "empty field in order_by")),
+    };
+    let desc = match words.next() {
+      None | Some("asc") => false,
+      Some("desc") => true,
+      Some(w) => {
+        return Err(syn::Error::new(
+          order_by.span(),
+          format!("expected asc or desc after `{}`, found `{}`", field, w),
+        ))
+      }
+    };
+    if words.next().is_some() {
+      return Err(syn::Error::new(
+        order_by.span(),
+        format!("unexpected token after `{}`", field),
+      ));
+    }
+    let pos = match names.iter().position(|n| n == field) {
+      Some(pos) => pos,
+      None => {
+        return Err(syn::Error::new(
+          order_by.span(),
+          format!("no field `{}` in {}", field, ast.ident),
+        ))
+      }
+    };
+    if order.iter().any(|(p, _)| *p == pos) {
+      return Err(syn::Error::new(
+        order_by.span(),
+        format!("field `{}` is listed twice", field),
+      ));
+    }
+    order.push((pos, desc));
+  }
+
+  for pos in 0..fields.len() {
+    if !order.iter().any(|(p, _)| *p == pos) {
+      order.push((pos, false));
+    }
+  }
+
+  let mut cmp = order.into_iter().map(|(pos, desc)| {
+    let f = &fields[pos];
+    if desc {
+      quote!(core::cmp::Ord::cmp(&b.#f, &a.#f))
+    } else {
+      quote!(core::cmp::Ord::cmp(&a.#f, &b.#f))
+    }
+  });
+
+  Ok(match cmp.next() {
+    None => quote!(core::cmp::Ordering::Equal),
+    Some(first) => quote!(#first #(.then_with(|| #cmp))*),
+  })
+}